@@ -0,0 +1,86 @@
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// An axis-aligned bounding box, defined by its minimum and maximum corners.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Aabb {
+    /// The corner with the smallest coordinates.
+    pub min: Vec3,
+    /// The corner with the largest coordinates.
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// The smallest box containing both a and b.
+    pub fn surrounding(a: &Aabb, b: &Aabb) -> Aabb {
+        Aabb {
+            min: Vec3 {
+                x: a.min.x.min(b.min.x),
+                y: a.min.y.min(b.min.y),
+                z: a.min.z.min(b.min.z),
+            },
+            max: Vec3 {
+                x: a.max.x.max(b.max.x),
+                y: a.max.y.max(b.max.y),
+                z: a.max.z.max(b.max.z),
+            },
+        }
+    }
+
+    /// The smallest box containing this box and the point p.
+    pub fn expand(&self, p: Vec3) -> Aabb {
+        Aabb::surrounding(self, &Aabb { min: p, max: p })
+    }
+
+    /// Grow the box by delta on every side.
+    pub fn pad(&self, delta: f64) -> Aabb {
+        Aabb {
+            min: self.min - Vec3::from_one(delta),
+            max: self.max + Vec3::from_one(delta),
+        }
+    }
+
+    /// The point at the center of the box.
+    pub fn centroid(&self) -> Vec3 {
+        0.5 * (self.min + self.max)
+    }
+
+    /// The total area of the six faces of the box.
+    pub fn surface_area(&self) -> f64 {
+        let d = self.max - self.min;
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    /// The axis along which the box is longest. 0, 1 and 2 are x, y and z respectively.
+    pub fn longest_axis(&self) -> usize {
+        let d = self.max - self.min;
+        if d.x > d.y && d.x > d.z {
+            0
+        } else if d.y > d.z {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// Test whether the ray passes through the box between times t_min and t_max.
+    ///
+    /// Uses the slab method: the ray is clipped against the pair of planes bounding the box on
+    /// each axis in turn, and misses if the remaining interval becomes empty.
+    pub fn hit(&self, ray: &Ray, mut t_min: f64, mut t_max: f64) -> bool {
+        for axis in 0..3 {
+            let inv_d = ray.direction[axis].recip();
+            let mut t0 = (self.min[axis] - ray.origin[axis]) * inv_d;
+            let mut t1 = (self.max[axis] - ray.origin[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            t_min = t0.max(t_min);
+            t_max = t1.min(t_max);
+            if t_max <= t_min {
+                return false;
+            }
+        }
+        true
+    }
+}
@@ -0,0 +1,203 @@
+//! Arbitrary output variables (AOVs): extra per-pixel buffers rendered alongside the image,
+//! describing what the camera rays first hit rather than how it was lit. They are handy for
+//! debugging scenes, and are the inputs a denoiser needs.
+
+use clap::ValueEnum;
+
+use crate::image::Image;
+use crate::sampler::Sampler;
+use crate::vec3::Vec3;
+
+/// The AOVs that can be rendered.
+#[derive(ValueEnum, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Aov {
+    /// The surface normal where camera rays first hit, facing the camera. Zero where nothing
+    /// was hit.
+    Normal,
+    /// The color of the surface first hit, ignoring lighting. The background where nothing was
+    /// hit.
+    Albedo,
+    /// The distance from the camera to the first hit. Zero where nothing was hit.
+    Depth,
+    /// Where camera rays first hit. Zero where nothing was hit.
+    Position,
+    /// The ID of the material first hit by the first sample of each pixel. Zero where nothing
+    /// was hit.
+    MaterialId,
+    /// The ID of the object first hit by the first sample of each pixel. Zero where nothing was
+    /// hit.
+    ObjectId,
+    /// The variance of each pixel's samples. High where the image is noisy.
+    Variance,
+}
+
+impl Aov {
+    /// The name of the AOV, used to name its layer or file.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aov::Normal => "normal",
+            Aov::Albedo => "albedo",
+            Aov::Depth => "depth",
+            Aov::Position => "position",
+            Aov::MaterialId => "material_id",
+            Aov::ObjectId => "object_id",
+            Aov::Variance => "variance",
+        }
+    }
+
+    /// The channels of the AOV's layer, or an empty list if it has a single value per pixel.
+    pub fn channels(&self) -> &'static [&'static str] {
+        match self {
+            Aov::Normal | Aov::Position => &["X", "Y", "Z"],
+            Aov::Albedo | Aov::Variance => &["R", "G", "B"],
+            Aov::Depth | Aov::MaterialId | Aov::ObjectId => &[],
+        }
+    }
+
+    /// The value of the AOV for a pixel, given everything accumulated for it. Single values are
+    /// repeated in all three components.
+    pub fn value(&self, pixel: &AovPixel, color_sum: Vec3, samples: usize) -> Vec3 {
+        let n = samples.max(1) as f64;
+        match self {
+            Aov::Normal => pixel.normal / n,
+            Aov::Albedo => pixel.albedo / n,
+            Aov::Depth => Vec3::from_one(pixel.depth / n),
+            Aov::Position => pixel.position / n,
+            Aov::MaterialId => Vec3::from_one(pixel.material_id as f64),
+            Aov::ObjectId => Vec3::from_one(pixel.object_id as f64),
+            Aov::Variance if samples < 2 => Vec3::zero(),
+            Aov::Variance => {
+                // The unbiased sample variance of each color component.
+                let mean = color_sum / n;
+                let variance = (pixel.color_squared - mean * color_sum) / (n - 1.0);
+                Vec3 {
+                    x: variance.x.max(0.0),
+                    y: variance.y.max(0.0),
+                    z: variance.z.max(0.0),
+                }
+            }
+        }
+    }
+
+    /// Turn an image of the AOV's raw values into colors that can be seen in an 8 bit image.
+    /// Normals are mapped from [-1, 1] to [0, 1], depth and position are scaled to fit the
+    /// range of the image, and each ID is given its own random color.
+    pub fn display(&self, image: &Image) -> Image {
+        let pixels: Vec<Vec3> = (0..image.height)
+            .flat_map(|j| (0..image.width).map(move |i| image.get(i, j)))
+            .collect();
+        let min = pixels
+            .iter()
+            .fold(Vec3::from_one(f64::INFINITY), |a, p| Vec3 {
+                x: a.x.min(p.x),
+                y: a.y.min(p.y),
+                z: a.z.min(p.z),
+            });
+        let max = pixels
+            .iter()
+            .fold(Vec3::from_one(f64::NEG_INFINITY), |a, p| Vec3 {
+                x: a.x.max(p.x),
+                y: a.y.max(p.y),
+                z: a.z.max(p.z),
+            });
+        let map = |p: Vec3| match self {
+            Aov::Normal => 0.5 * (p + Vec3::from_one(1.0)),
+            Aov::Depth if max.x > 0.0 => p / max.x,
+            Aov::Position => {
+                let range = max - min;
+                let scale = range.x.max(range.y).max(range.z);
+                if scale > 0.0 {
+                    (p - min) / scale
+                } else {
+                    Vec3::zero()
+                }
+            }
+            Aov::MaterialId | Aov::ObjectId => id_color(p.x as usize),
+            _ => p,
+        };
+        let mut display = Image::make(image.width, image.height);
+        for (index, p) in pixels.into_iter().enumerate() {
+            display.set(index % image.width, index / image.width, map(p));
+        }
+        display
+    }
+}
+
+/// A random but repeatable color for an ID, so neighbouring IDs are easy to tell apart. Zero,
+/// meaning nothing, is black.
+fn id_color(id: usize) -> Vec3 {
+    if id == 0 {
+        return Vec3::zero();
+    }
+    Vec3::random(&mut Sampler::make(id as u64), 0.2, 1.0)
+}
+
+/// What a camera ray first hit, or the sums of that over all of a pixel's samples. Everything
+/// the AOVs are made from.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AovPixel {
+    /// The surface normal, facing the camera.
+    pub normal: Vec3,
+    /// The color of the surface ignoring lighting.
+    pub albedo: Vec3,
+    /// Where the ray hit.
+    pub position: Vec3,
+    /// The distance from the camera.
+    pub depth: f64,
+    /// The squares of the colors of the samples, for working out their variance.
+    pub color_squared: Vec3,
+    /// The ID of the object hit by the first sample.
+    pub object_id: usize,
+    /// The ID of the material hit by the first sample.
+    pub material_id: usize,
+}
+
+impl AovPixel {
+    /// A pixel with nothing accumulated.
+    pub fn zero() -> AovPixel {
+        AovPixel {
+            normal: Vec3::zero(),
+            albedo: Vec3::zero(),
+            position: Vec3::zero(),
+            depth: 0.0,
+            color_squared: Vec3::zero(),
+            object_id: 0,
+            material_id: 0,
+        }
+    }
+
+    /// Add another sample, or sum of samples, to this one. IDs are kept from the first sample,
+    /// so are only taken from other if this pixel has no samples yet.
+    pub fn add(&mut self, other: &AovPixel, first: bool) {
+        self.normal = self.normal + other.normal;
+        self.albedo = self.albedo + other.albedo;
+        self.position = self.position + other.position;
+        self.depth += other.depth;
+        self.color_squared = self.color_squared + other.color_squared;
+        if first {
+            self.object_id = other.object_id;
+            self.material_id = other.material_id;
+        }
+    }
+}
+
+#[test]
+fn test_variance_of_samples() {
+    let colors = [1.0, 2.0, 3.0, 6.0];
+    let mut pixel = AovPixel::zero();
+    let mut sum = Vec3::zero();
+    for (index, &c) in colors.iter().enumerate() {
+        let color = Vec3::from_one(c);
+        sum = sum + color;
+        pixel.add(
+            &AovPixel {
+                color_squared: color * color,
+                ..AovPixel::zero()
+            },
+            index == 0,
+        );
+    }
+    // The mean is 3, so the variance is (4 + 1 + 0 + 9) / 3.
+    let variance = Aov::Variance.value(&pixel, sum, colors.len());
+    assert!((variance.x - 14.0 / 3.0).abs() < 1e-12);
+}
@@ -0,0 +1,50 @@
+//! The SDL viewer. Shows a scene in a window as it renders, with controls to move the camera
+//! around, and writes the image to a file as well if asked to.
+
+use std::process;
+
+use clap::Parser;
+
+use rays::bvh::BvhNode;
+use rays::cli::Args;
+use rays::output::window::WindowOutput;
+use rays::output::Output;
+use rays::render::render;
+
+fn main() {
+    let args = Args::parse();
+
+    let (scene, settings) = args
+        .scene()
+        .unwrap_or_else(|error| exit(&format!("Failed to load scene: {}", error)));
+    let mut outputs: Vec<Box<dyn Output>> = vec![Box::from(WindowOutput::make(
+        settings.width,
+        settings.height,
+        scene.camera,
+    ))];
+    match args.file_output(None) {
+        Ok(Some(output)) => outputs.push(Box::from(output)),
+        Ok(None) => {}
+        Err(message) => exit(&message),
+    }
+    let world = BvhNode::make(scene.world);
+
+    // Render until the outputs are happy, starting again whenever the camera is moved.
+    let mut camera_settings = scene.camera;
+    let frame = loop {
+        let camera = camera_settings.camera(settings.width as f64 / settings.height as f64);
+        let frame = render(&camera, &world, &settings, &mut outputs);
+        match outputs.next_camera() {
+            Some(moved) => camera_settings = moved,
+            None => break frame,
+        }
+    };
+    if let Err(error) = outputs.finish(&frame) {
+        exit(&format!("Failed to write image: {}", error));
+    }
+}
+
+fn exit(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
@@ -0,0 +1,404 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hit::{HitOrMiss, Hittable, HittableList};
+use crate::light::Light;
+use crate::medium::Medium;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// The number of buckets objects are sorted into along an axis when looking for the best split.
+const SAH_BUCKETS: usize = 12;
+
+/// Nodes with at most this many objects may become leaves if splitting them is not worth it.
+const MAX_LEAF_SIZE: usize = 4;
+
+/// The cost of testing a ray against a node's bounding box, relative to testing an object.
+const TRAVERSAL_COST: f64 = 0.125;
+
+/// A bounding volume hierarchy. A tree of bounding boxes over a set of Hittables, which lets a
+/// ray skip every object inside a box it misses.
+pub enum BvhNode {
+    /// A node holding a few objects, which are all tested against rays that hit its box.
+    Leaf {
+        /// The box around all of the objects.
+        bbox: Aabb,
+        /// The objects in the node.
+        hittables: Vec<Box<dyn Hittable>>,
+    },
+    /// A node whose objects are split between two child nodes.
+    Branch {
+        /// The box around both children.
+        bbox: Aabb,
+        /// One child node.
+        left: Box<BvhNode>,
+        /// The other child node.
+        right: Box<BvhNode>,
+    },
+    /// The root of a tree over a world with objects that have no bounding box, such as planes.
+    /// They are tested against every ray, alongside the tree over everything else.
+    Unbounded {
+        /// The objects with no bounding box.
+        hittables: Vec<Box<dyn Hittable>>,
+        /// The tree over the other objects, if there are any.
+        rest: Option<Box<BvhNode>>,
+    },
+}
+
+/// An object waiting to be placed in the tree, with its bounding box cached.
+struct Primitive {
+    hittable: Box<dyn Hittable>,
+    bbox: Aabb,
+    centroid: Vec3,
+}
+
+impl BvhNode {
+    /// Build a BVH over all the objects in the list, using the surface area heuristic to decide
+    /// how to split them.
+    ///
+    /// Objects with no bounding box are kept out of the tree and tested against every ray.
+    ///
+    /// Panics if the list is empty.
+    pub fn make(list: HittableList) -> BvhNode {
+        assert!(
+            !list.hittables.is_empty(),
+            "Cannot build a BVH over an empty list"
+        );
+        let mut unbounded = vec![];
+        let mut primitives = vec![];
+        for hittable in list.hittables {
+            match hittable.bounding_box() {
+                Some(bbox) => primitives.push(Primitive {
+                    hittable,
+                    bbox,
+                    centroid: bbox.centroid(),
+                }),
+                None => unbounded.push(hittable),
+            }
+        }
+        if unbounded.is_empty() {
+            return BvhNode::build(primitives);
+        }
+        BvhNode::Unbounded {
+            hittables: unbounded,
+            rest: if primitives.is_empty() {
+                None
+            } else {
+                Some(Box::from(BvhNode::build(primitives)))
+            },
+        }
+    }
+
+    fn build(primitives: Vec<Primitive>) -> BvhNode {
+        let bbox = primitives
+            .iter()
+            .skip(1)
+            .fold(primitives[0].bbox, |b, p| Aabb::surrounding(&b, &p.bbox));
+        let centroid_bounds = primitives.iter().skip(1).fold(
+            Aabb {
+                min: primitives[0].centroid,
+                max: primitives[0].centroid,
+            },
+            |b, p| b.expand(p.centroid),
+        );
+        let axis = centroid_bounds.longest_axis();
+        let (lo, hi) = (centroid_bounds.min[axis], centroid_bounds.max[axis]);
+
+        // Every centroid is in the same place, so there is no way to separate the objects.
+        if primitives.len() == 1 || hi <= lo {
+            return BvhNode::leaf(bbox, primitives);
+        }
+
+        let bucket_of = |p: &Primitive| {
+            (((p.centroid[axis] - lo) / (hi - lo) * SAH_BUCKETS as f64) as usize)
+                .min(SAH_BUCKETS - 1)
+        };
+
+        // Sort the objects into buckets along the axis.
+        let mut counts = [0usize; SAH_BUCKETS];
+        let mut bounds: [Option<Aabb>; SAH_BUCKETS] = [None; SAH_BUCKETS];
+        for p in &primitives {
+            let b = bucket_of(p);
+            counts[b] += 1;
+            bounds[b] = Some(match bounds[b] {
+                Some(existing) => Aabb::surrounding(&existing, &p.bbox),
+                None => p.bbox,
+            });
+        }
+
+        // Estimate the cost of splitting after each bucket. The chance of a ray hitting a child
+        // is proportional to its surface area relative to the parent.
+        let mut best_split = 0;
+        let mut best_cost = f64::INFINITY;
+        for split in 0..SAH_BUCKETS - 1 {
+            let (left_count, left_area) = Self::bucket_totals(&counts[..=split], &bounds[..=split]);
+            let (right_count, right_area) =
+                Self::bucket_totals(&counts[split + 1..], &bounds[split + 1..]);
+            if left_count == 0 || right_count == 0 {
+                continue;
+            }
+            let cost = TRAVERSAL_COST
+                + (left_count as f64 * left_area + right_count as f64 * right_area)
+                    / bbox.surface_area();
+            if cost < best_cost {
+                best_cost = cost;
+                best_split = split;
+            }
+        }
+
+        // Testing every object in a leaf costs 1 per object.
+        if primitives.len() <= MAX_LEAF_SIZE && primitives.len() as f64 <= best_cost {
+            return BvhNode::leaf(bbox, primitives);
+        }
+
+        let (left, right): (Vec<Primitive>, Vec<Primitive>) = primitives
+            .into_iter()
+            .partition(|p| bucket_of(p) <= best_split);
+        BvhNode::Branch {
+            bbox,
+            left: Box::from(BvhNode::build(left)),
+            right: Box::from(BvhNode::build(right)),
+        }
+    }
+
+    /// The number of objects in the buckets, and the surface area of the box around them.
+    fn bucket_totals(counts: &[usize], bounds: &[Option<Aabb>]) -> (usize, f64) {
+        let count = counts.iter().sum();
+        let area = bounds
+            .iter()
+            .flatten()
+            .copied()
+            .reduce(|a, b| Aabb::surrounding(&a, &b))
+            .map_or(0.0, |b| b.surface_area());
+        (count, area)
+    }
+
+    fn leaf(bbox: Aabb, primitives: Vec<Primitive>) -> BvhNode {
+        BvhNode::Leaf {
+            bbox,
+            hittables: primitives.into_iter().map(|p| p.hittable).collect(),
+        }
+    }
+}
+
+impl Hittable for BvhNode {
+    /// Test whether the ray hits any object in the tree, only descending into nodes whose
+    /// bounding box the ray passes through.
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> HitOrMiss<'_> {
+        match self {
+            BvhNode::Leaf { bbox, hittables } => {
+                if !bbox.hit(ray, t_min, t_max) {
+                    return HitOrMiss::Miss;
+                }
+                closest_hit(hittables, ray, t_min, t_max)
+            }
+            BvhNode::Branch { bbox, left, right } => {
+                if !bbox.hit(ray, t_min, t_max) {
+                    return HitOrMiss::Miss;
+                }
+                match left.hit(ray, t_min, t_max) {
+                    // Anything in the right node must be closer than the left hit to count.
+                    left_hit @ HitOrMiss::Hit { t, .. } => match right.hit(ray, t_min, t) {
+                        HitOrMiss::Miss => left_hit,
+                        right_hit => right_hit,
+                    },
+                    HitOrMiss::Miss => right.hit(ray, t_min, t_max),
+                }
+            }
+            BvhNode::Unbounded { hittables, rest } => {
+                let unbounded_hit = closest_hit(hittables, ray, t_min, t_max);
+                let closest = match unbounded_hit {
+                    HitOrMiss::Hit { t, .. } => t,
+                    HitOrMiss::Miss => t_max,
+                };
+                match rest.as_ref().map(|rest| rest.hit(ray, t_min, closest)) {
+                    Some(rest_hit @ HitOrMiss::Hit { .. }) => rest_hit,
+                    _ => unbounded_hit,
+                }
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        match self {
+            BvhNode::Leaf { bbox, .. } | BvhNode::Branch { bbox, .. } => Some(*bbox),
+            BvhNode::Unbounded { .. } => None,
+        }
+    }
+
+    fn lights(&self) -> Vec<Box<dyn Light>> {
+        match self {
+            BvhNode::Leaf { hittables, .. } => hittables
+                .iter()
+                .flat_map(|hittable| hittable.lights())
+                .collect(),
+            BvhNode::Branch { left, right, .. } => {
+                let mut lights = left.lights();
+                lights.extend(right.lights());
+                lights
+            }
+            BvhNode::Unbounded { hittables, rest } => {
+                let mut lights: Vec<Box<dyn Light>> = hittables
+                    .iter()
+                    .flat_map(|hittable| hittable.lights())
+                    .collect();
+                if let Some(rest) = rest {
+                    lights.extend(rest.lights());
+                }
+                lights
+            }
+        }
+    }
+
+    fn media(&self) -> Vec<Arc<dyn Medium>> {
+        match self {
+            BvhNode::Leaf { hittables, .. } => hittables
+                .iter()
+                .flat_map(|hittable| hittable.media())
+                .collect(),
+            BvhNode::Branch { left, right, .. } => {
+                let mut media = left.media();
+                media.extend(right.media());
+                media
+            }
+            BvhNode::Unbounded { hittables, rest } => {
+                let mut media: Vec<Arc<dyn Medium>> = hittables
+                    .iter()
+                    .flat_map(|hittable| hittable.media())
+                    .collect();
+                if let Some(rest) = rest {
+                    media.extend(rest.media());
+                }
+                media
+            }
+        }
+    }
+}
+
+/// The closest hit among a few objects, testing each in turn.
+fn closest_hit<'a>(
+    hittables: &'a [Box<dyn Hittable>],
+    ray: &Ray,
+    t_min: f64,
+    t_max: f64,
+) -> HitOrMiss<'a> {
+    let mut hit_or_miss = HitOrMiss::Miss;
+    let mut closest = t_max;
+    for hittable in hittables {
+        let tmp = hittable.hit(ray, t_min, closest);
+        if let HitOrMiss::Hit { t, .. } = tmp {
+            closest = t;
+            hit_or_miss = tmp;
+        }
+    }
+    hit_or_miss
+}
+
+#[test]
+fn test_bvh_hits_same_objects_as_list() {
+    use crate::material::lambertian::Lambertian;
+    use crate::sampler::Sampler;
+    use crate::sphere::Sphere;
+
+    let spheres = || {
+        let mut list = HittableList { hittables: vec![] };
+        for a in -5..5 {
+            for b in -5..5 {
+                list.hittables.push(Box::from(Sphere {
+                    center: Vec3 {
+                        x: a as f64,
+                        y: 0.0,
+                        z: b as f64 + 0.3 * a as f64,
+                    },
+                    r: 0.4,
+                    material: Box::from(Lambertian::make(Vec3::from_one(0.5))),
+                }));
+            }
+        }
+        list
+    };
+    let list = spheres();
+    let bvh = BvhNode::make(spheres());
+
+    let mut sampler = Sampler::make(0);
+    for _ in 0..1000 {
+        let ray = Ray {
+            origin: Vec3 {
+                x: 0.0,
+                y: 10.0,
+                z: 0.0,
+            },
+            direction: Vec3::random(&mut sampler, -1.0, 1.0),
+            time: 0.0,
+        };
+        let t_of = |hit_or_miss: HitOrMiss<'_>| match hit_or_miss {
+            HitOrMiss::Hit { t, .. } => Some(t),
+            HitOrMiss::Miss => None,
+        };
+        assert_eq!(
+            t_of(list.hit(&ray, 0.001, f64::INFINITY)),
+            t_of(bvh.hit(&ray, 0.001, f64::INFINITY))
+        );
+    }
+}
+
+#[test]
+fn test_bvh_tests_unbounded_objects_against_every_ray() {
+    use crate::material::lambertian::Lambertian;
+    use crate::plane::Plane;
+    use crate::sampler::Sampler;
+    use crate::sphere::Sphere;
+
+    // Spheres half sunk into a ground plane.
+    let world = || {
+        let mut list = HittableList { hittables: vec![] };
+        for a in -3..3 {
+            list.hittables.push(Box::from(Sphere {
+                center: Vec3 {
+                    x: a as f64,
+                    y: 0.0,
+                    z: -0.5 * a as f64,
+                },
+                r: 0.4,
+                material: Box::from(Lambertian::make(Vec3::from_one(0.5))),
+            }));
+        }
+        list.hittables.push(Box::from(Plane::make(
+            Vec3::zero(),
+            Vec3 {
+                x: 0.0,
+                y: 1.0,
+                z: 0.0,
+            },
+            Box::from(Lambertian::make(Vec3::from_one(0.5))),
+        )));
+        list
+    };
+    let list = world();
+    let bvh = BvhNode::make(world());
+    assert!(bvh.bounding_box().is_none());
+
+    let mut sampler = Sampler::make(0);
+    let mut plane_hits = 0;
+    for _ in 0..1000 {
+        let ray = Ray {
+            origin: Vec3 {
+                x: 0.0,
+                y: 3.0,
+                z: 5.0,
+            },
+            direction: Vec3::random(&mut sampler, -1.0, 1.0),
+            time: 0.0,
+        };
+        let t_of = |hit_or_miss: HitOrMiss<'_>| match hit_or_miss {
+            HitOrMiss::Hit { t, .. } => Some(t),
+            HitOrMiss::Miss => None,
+        };
+        let t = t_of(list.hit(&ray, 0.001, f64::INFINITY));
+        assert_eq!(t, t_of(bvh.hit(&ray, 0.001, f64::INFINITY)));
+        if t.is_some_and(|t| ray.at(t).y.abs() < 1e-9) {
+            plane_hits += 1;
+        }
+    }
+    assert!(plane_hits > 100);
+}
@@ -1,6 +1,101 @@
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::vec3::Vec3;
 
+/// Everything about a Camera except the shape of the image it takes, so the camera can be built
+/// once the image size is known, and rebuilt if it changes.
+#[derive(Copy, Clone, Debug)]
+pub struct CameraSettings {
+    /// Where the camera is.
+    pub lookfrom: Vec3,
+    /// The point the camera looks at, which will be in the center of the image.
+    pub lookat: Vec3,
+    /// Which way is up. The camera is rolled so this points up in the image.
+    pub vup: Vec3,
+    /// Vertical field of view, in degrees.
+    pub vfov: f64,
+    /// The diameter of the lens. Larger apertures blur things away from the focus distance more.
+    /// Zero gives a pinhole camera with everything in focus.
+    pub aperture: f64,
+    /// The distance from the camera at which things are perfectly in focus.
+    pub focus_dist: f64,
+    /// When the shutter opens. Moving objects move from where they are at time 0 to where they
+    /// are at time 1.
+    pub shutter_open: f64,
+    /// When the shutter closes. Objects moving while it is open are blurred along their path.
+    /// The same as shutter_open for a camera that freezes everything at that moment.
+    pub shutter_close: f64,
+}
+
+impl CameraSettings {
+    /// Build a Camera for an image with the given aspect ratio.
+    pub fn camera(&self, aspect_ratio: f64) -> Camera {
+        Camera::camera(
+            self.lookfrom,
+            self.lookat,
+            self.vup,
+            self.vfov,
+            aspect_ratio,
+            self.aperture,
+            self.focus_dist,
+        )
+        .with_shutter(self.shutter_open, self.shutter_close)
+    }
+}
+
+/// Controls for moving the camera around interactively, used by the window.
+impl CameraSettings {
+    /// Move the camera and the point it looks at together: forward towards lookat, right, and up
+    /// along vup.
+    pub fn fly(&self, forward: f64, right: f64, up: f64) -> CameraSettings {
+        let (forward_axis, right_axis, up_axis) = self.axes();
+        let offset = forward * forward_axis + right * right_axis + up * up_axis;
+        CameraSettings {
+            lookfrom: self.lookfrom + offset,
+            lookat: self.lookat + offset,
+            ..*self
+        }
+    }
+
+    /// Swing the camera around lookat, keeping its distance. yaw turns it around vup, and pitch
+    /// tilts it down, both in radians. The camera stops a degree short of passing over the top or
+    /// bottom, where vup would no longer tell it which way is up.
+    pub fn orbit(&self, yaw: f64, pitch: f64) -> CameraSettings {
+        let (_, right_axis, up_axis) = self.axes();
+        let offset = self.lookfrom - self.lookat;
+        let elevation = offset.normalize().dot(&up_axis).clamp(-1.0, 1.0).asin();
+        let limit = 89f64.to_radians();
+        let pitch = elevation - (elevation - pitch).clamp(-limit, limit);
+        let offset = rotate(rotate(offset, right_axis, pitch), up_axis, yaw);
+        CameraSettings {
+            lookfrom: self.lookat + offset,
+            ..*self
+        }
+    }
+
+    /// Narrow the field of view by the given factor, zooming in if it is greater than one.
+    pub fn zoom(&self, factor: f64) -> CameraSettings {
+        CameraSettings {
+            vfov: (self.vfov / factor).clamp(1.0, 179.0),
+            ..*self
+        }
+    }
+
+    /// Unit vectors pointing forwards, right and up from the camera.
+    fn axes(&self) -> (Vec3, Vec3, Vec3) {
+        let up = self.vup.normalize();
+        let forward = (self.lookat - self.lookfrom).normalize();
+        (forward, forward.cross(&up).normalize(), up)
+    }
+}
+
+/// Rotate v by angle radians around the unit vector axis, using Rodrigues' formula.
+fn rotate(v: Vec3, axis: Vec3, angle: f64) -> Vec3 {
+    let (sin, cos) = angle.sin_cos();
+    v * cos + axis.cross(&v) * sin + axis * axis.dot(&v) * (1.0 - cos)
+}
+
+/// A thin lens camera, which generates the rays shot into the scene for each point on the image.
 pub struct Camera {
     origin: Vec3,
     horizontal: Vec3,
@@ -9,10 +104,14 @@ pub struct Camera {
     lens_radius: f64,
     u: Vec3,
     v: Vec3,
-    w: Vec3,
+    shutter_open: f64,
+    shutter_close: f64,
 }
 
 impl Camera {
+    /// Create a camera at lookfrom looking at lookat. See CameraSettings for the meaning of each
+    /// argument.
+    #[allow(clippy::self_named_constructors)]
     pub fn camera(lookfrom: Vec3, lookat: Vec3, vup: Vec3, vfov: f64, aspect_ratio: f64, aperture: f64, focus_dist: f64) -> Camera {
         let theta = vfov.to_radians();
         let h = (theta / 2.0).tan();
@@ -34,17 +133,68 @@ impl Camera {
             lens_radius: aperture / 2.0,
             u,
             v,
-            w,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
         }
     }
 
-    pub fn get_ray(&self, s: f64, t: f64) -> Ray {
-        let rd = Vec3::random_in_unit_disk() * self.lens_radius;
+    /// The same camera with its shutter open from time open to time close, rather than taking
+    /// everything at time 0.
+    pub fn with_shutter(self, open: f64, close: f64) -> Camera {
+        Camera {
+            shutter_open: open,
+            shutter_close: close,
+            ..self
+        }
+    }
+
+    /// The ray through the point (s, t) on the image, where (0, 0) is the bottom left corner and
+    /// (1, 1) the top right. The ray starts from a random point on the lens, at a random moment
+    /// while the shutter is open.
+    pub fn get_ray(&self, s: f64, t: f64, sampler: &mut Sampler) -> Ray {
+        let rd = Vec3::random_in_unit_disk(sampler) * self.lens_radius;
         let offset = self.u * rd.x + self.v * rd.y;
+        // No random number is used for a shutter that doesn't stay open, so still images are the
+        // same as with cameras that have no shutter.
+        let time = if self.shutter_close > self.shutter_open {
+            self.shutter_open + (self.shutter_close - self.shutter_open) * sampler.random_double()
+        } else {
+            self.shutter_open
+        };
         Ray {
             origin: self.origin + offset,
             direction: self.lower_left_corner + s * self.horizontal + t * self.vertical
                 - self.origin - offset,
+            time,
         }
     }
 }
+
+#[test]
+fn test_orbit_keeps_distance_and_stays_upright() {
+    let settings = CameraSettings {
+        lookfrom: Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: 5.0,
+        },
+        lookat: Vec3::zero(),
+        vup: Vec3 {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        },
+        vfov: 20.0,
+        aperture: 0.0,
+        focus_dist: 5.0,
+        shutter_open: 0.0,
+        shutter_close: 0.0,
+    };
+    let orbited = settings.orbit(1.0, 0.5);
+    assert!(((orbited.lookfrom - orbited.lookat).magnitude() - 5.0).abs() < 1e-9);
+    assert!(orbited.lookfrom.y < 0.0);
+
+    // Pitching too far stops the camera just short of the top, rather than flipping it over.
+    let orbited = settings.orbit(0.0, -3.0);
+    assert!(orbited.lookfrom.y > 4.99 && orbited.lookfrom.z > 0.0);
+}
@@ -0,0 +1,190 @@
+//! The command line options shared by the rays binaries, and turning them into a scene and the
+//! settings to render it with.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+
+use clap::Parser;
+
+use crate::aov::Aov;
+use crate::environment::{Environment, EnvironmentMap};
+use crate::image::Image;
+use crate::integrator::{IntegratorKind, ROULETTE_DEPTH};
+use crate::output::{FileOutput, Format};
+use crate::render::Settings;
+use crate::sampler::Sampler;
+use crate::scene;
+use crate::scene::{Scene, SceneError};
+use crate::vec3::Vec3;
+
+/// Render a scene with ray tracing.
+///
+/// Renders the random scene from the end of Ray Tracing In One Weekend unless a scene file is
+/// given. Settings given here override those in the scene file.
+#[derive(Parser, Debug)]
+#[command(version)]
+pub struct Args {
+    /// A TOML scene file to render.
+    pub scene: Option<PathBuf>,
+
+    /// Write the finished image to this file. The headless renderer defaults to render.png.
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// The format of the output file. Chosen from the output file extension if not given.
+    #[arg(short, long, value_enum)]
+    pub format: Option<Format>,
+
+    /// Width of the image in pixels. If only one of width and height is given, the other is
+    /// chosen to keep the aspect ratio of the scene.
+    #[arg(long, value_parser = positive)]
+    pub width: Option<usize>,
+
+    /// Height of the image in pixels.
+    #[arg(long, value_parser = positive)]
+    pub height: Option<usize>,
+
+    /// Number of rays traced through each pixel.
+    #[arg(short, long = "samples", value_name = "SAMPLES", value_parser = positive)]
+    pub samples_per_pixel: Option<usize>,
+
+    /// Maximum number of times a ray may bounce before it is considered absorbed.
+    #[arg(short = 'd', long, value_parser = positive)]
+    pub max_depth: Option<usize>,
+
+    /// How the light arriving along each ray is worked out.
+    #[arg(long, value_enum, default_value = "path")]
+    pub integrator: IntegratorKind,
+
+    /// Number of bounces after which paths carrying little light may be ended early with
+    /// Russian roulette. Paths that carry on are brightened to make up for those that don't, so
+    /// the image is the same on average, only noisier.
+    #[arg(long, default_value_t = ROULETTE_DEPTH)]
+    pub roulette_depth: usize,
+
+    /// Number of render threads. Defaults to one per CPU core.
+    #[arg(short = 'j', long, value_parser = positive)]
+    pub threads: Option<usize>,
+
+    /// Replace the scene's background with a single color, given as R,G,B with components from
+    /// 0 to 1. Use 0,0,0 for a black background.
+    #[arg(long, value_name = "R,G,B", value_parser = color)]
+    pub background: Option<Vec3>,
+
+    /// Light the scene with an equirectangular .hdr or .exr image of its surroundings, replacing
+    /// the scene's background.
+    #[arg(long, value_name = "FILE", conflicts_with = "background")]
+    pub environment: Option<PathBuf>,
+
+    /// Seed for the random numbers used to build and render the scene. The same seed always gives
+    /// the same image, whatever the number of threads. Chosen at random if not given.
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Render the whole image one sample per pixel at a time, refining it with each pass until
+    /// the number of samples is reached or the render is stopped.
+    #[arg(short, long)]
+    pub progressive: bool,
+
+    /// Extra buffers to render alongside the image, separated by commas. EXR output holds them
+    /// as layers of the image file, other formats write each to its own file, such as
+    /// render.normal.png.
+    #[arg(long = "aov", value_name = "AOVS", value_enum, value_delimiter = ',')]
+    pub aovs: Vec<Aov>,
+}
+
+impl Args {
+    /// Load the scene file, or build the default scene if there isn't one, along with the
+    /// settings to render it with. Options given on the command line replace the scene's.
+    pub fn scene(&self) -> Result<(Scene, Settings), SceneError> {
+        // Every random number in the render comes from this seed, so the same seed always gives
+        // the same image.
+        let seed = self.seed.unwrap_or_else(rand::random);
+        let scene = match &self.scene {
+            Some(path) => scene::load(path)?,
+            None => scene::default_scene(&mut Sampler::make(seed)),
+        };
+
+        // Resolution given on the command line replaces the scene's, keeping the scene's aspect
+        // ratio if only one side is given.
+        let aspect_ratio = scene.width as f64 / scene.height as f64;
+        let (width, height) = match (self.width, self.height) {
+            (Some(width), Some(height)) => (width, height),
+            (Some(width), None) => (width, ((width as f64 / aspect_ratio) as usize).max(1)),
+            (None, Some(height)) => (((height as f64 * aspect_ratio) as usize).max(1), height),
+            (None, None) => (scene.width, scene.height),
+        };
+        let environment = match (&self.environment, self.background) {
+            (Some(path), _) => {
+                let image = Image::load(path).map_err(|error| SceneError::Io {
+                    path: path.clone(),
+                    error,
+                })?;
+                Environment::Map(Arc::new(EnvironmentMap::make(image, 0.0, 1.0)))
+            }
+            (None, Some(color)) => Environment::Solid(color),
+            (None, None) => scene.environment.clone(),
+        };
+        let settings = Settings {
+            width,
+            height,
+            samples_per_pixel: self.samples_per_pixel.unwrap_or(scene.samples_per_pixel),
+            integrator: self.integrator.make(
+                self.max_depth.unwrap_or(scene.max_depth),
+                self.roulette_depth,
+            ),
+            threads: self
+                .threads
+                .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get())),
+            environment,
+            seed,
+            progressive: self.progressive,
+            aovs: self.aovs.clone(),
+        };
+        Ok((scene, settings))
+    }
+
+    /// The output writing the image to the file given with --output, or to the default path if
+    /// there is one and no file was given.
+    pub fn file_output(&self, default: Option<&str>) -> Result<Option<FileOutput>, String> {
+        let path = match self.output.clone().or_else(|| default.map(PathBuf::from)) {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+        let format = self
+            .format
+            .or_else(|| Format::from_path(&path))
+            .ok_or_else(|| {
+                format!(
+                    "Cannot tell the format of {} from its extension. Use --format to choose one",
+                    path.display()
+                )
+            })?;
+        Ok(Some(FileOutput::make(&path, format)))
+    }
+}
+
+fn positive(s: &str) -> Result<usize, String> {
+    match s.parse() {
+        Ok(0) => Err("must be greater than zero".to_string()),
+        Ok(n) => Ok(n),
+        Err(_) => Err(format!("'{}' is not a whole number", s)),
+    }
+}
+
+fn color(s: &str) -> Result<Vec3, String> {
+    let components = s
+        .split(',')
+        .map(|c| c.trim().parse::<f64>())
+        .collect::<Result<Vec<f64>, _>>()
+        .map_err(|_| format!("'{}' is not a list of numbers", s))?;
+    match components[..] {
+        [x, y, z] if components.iter().all(|c| *c >= 0.0) => Ok(Vec3 { x, y, z }),
+        [_, _, _] => Err("color components cannot be negative".to_string()),
+        _ => Err(format!(
+            "expected 3 components but found {}",
+            components.len()
+        )),
+    }
+}
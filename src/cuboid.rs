@@ -0,0 +1,145 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hit::{HitOrMiss, Hittable, HittableList};
+use crate::light::Light;
+use crate::material::Material;
+use crate::quad::Quad;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// An axis-aligned box, made of six Quads sharing one material. Each side faces out of the box
+/// and has texture coordinates running from 0 to 1 across it.
+pub struct Cuboid {
+    sides: HittableList,
+    bbox: Aabb,
+}
+
+impl Cuboid {
+    /// Create a box with opposite corners at a and b.
+    pub fn make(a: Vec3, b: Vec3, material: Box<dyn Material>) -> Cuboid {
+        let min = Vec3 {
+            x: a.x.min(b.x),
+            y: a.y.min(b.y),
+            z: a.z.min(b.z),
+        };
+        let max = Vec3 {
+            x: a.x.max(b.x),
+            y: a.y.max(b.y),
+            z: a.z.max(b.z),
+        };
+        let d = max - min;
+        let dx = Vec3 {
+            x: d.x,
+            y: 0.0,
+            z: 0.0,
+        };
+        let dy = Vec3 {
+            x: 0.0,
+            y: d.y,
+            z: 0.0,
+        };
+        let dz = Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: d.z,
+        };
+
+        let material: Arc<dyn Material> = Arc::from(material);
+        // Each side's u x v points out of the box.
+        let sides = [
+            (min + dz, dx, dy),       // Front, facing +z.
+            (min + dx + dz, -dz, dy), // Right, facing +x.
+            (min + dx, -dx, dy),      // Back, facing -z.
+            (min, dz, dy),            // Left, facing -x.
+            (min + dy + dz, dx, -dz), // Top, facing +y.
+            (min, dx, dz),            // Bottom, facing -y.
+        ];
+        Cuboid {
+            sides: HittableList {
+                hittables: sides
+                    .iter()
+                    .map(|&(corner, u, v)| {
+                        Box::from(Quad::make(corner, u, v, Box::from(material.clone())))
+                            as Box<dyn Hittable>
+                    })
+                    .collect(),
+            },
+            bbox: Aabb { min, max }.pad(1e-4),
+        }
+    }
+}
+
+impl Hittable for Cuboid {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> HitOrMiss<'_> {
+        if !self.bbox.hit(ray, t_min, t_max) {
+            return HitOrMiss::Miss;
+        }
+        self.sides.hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.bbox)
+    }
+
+    fn lights(&self) -> Vec<Box<dyn Light>> {
+        self.sides.lights()
+    }
+}
+
+#[test]
+fn test_cuboid_sides_face_out() {
+    use crate::material::lambertian::Lambertian;
+
+    let cuboid = Cuboid::make(
+        Vec3 {
+            x: 1.0,
+            y: 2.0,
+            z: 3.0,
+        },
+        Vec3::from_one(-1.0),
+        Box::from(Lambertian::make(Vec3::from_one(0.5))),
+    );
+    let center = Vec3 {
+        x: 0.0,
+        y: 0.5,
+        z: 1.0,
+    };
+    // Rays from outside each side towards the center hit the front of that side, and rays from
+    // the center hit the back of it.
+    for axis in 0..3 {
+        for sign in [-1.0, 1.0] {
+            let mut out = Vec3::zero();
+            match axis {
+                0 => out.x = sign,
+                1 => out.y = sign,
+                _ => out.z = sign,
+            }
+            let inward = Ray {
+                origin: center + 10.0 * out,
+                direction: -out,
+                time: 0.0,
+            };
+            match cuboid.hit(&inward, 0.001, f64::INFINITY) {
+                HitOrMiss::Hit { point, .. } => {
+                    assert!(point.front_face);
+                    assert_eq!(out, point.normal);
+                    assert!((0.0..=1.0).contains(&point.u) && (0.0..=1.0).contains(&point.v));
+                }
+                HitOrMiss::Miss => panic!("Expected a hit from outside along {:?}", out),
+            }
+            let outward = Ray {
+                origin: center,
+                direction: out,
+                time: 0.0,
+            };
+            match cuboid.hit(&outward, 0.001, f64::INFINITY) {
+                HitOrMiss::Hit { point, .. } => {
+                    assert!(!point.front_face);
+                    assert_eq!(-out, point.normal);
+                }
+                HitOrMiss::Miss => panic!("Expected a hit from inside along {:?}", out),
+            }
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use crate::aabb::Aabb;
+use crate::hit::{HitOrMiss, Hittable};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// A flat circle, like the top of a cylinder.
+pub struct Disk {
+    center: Vec3,
+    normal: Vec3,
+    radius: f64,
+    /// Axes along the disk, for texture coordinates.
+    axes: (Vec3, Vec3),
+    material: Box<dyn Material>,
+}
+
+impl Disk {
+    /// Create a disk around center with the given radius, facing out along normal. Texture
+    /// coordinates are polar: u runs once around the disk, and v from the center to the edge.
+    pub fn make(center: Vec3, normal: Vec3, radius: f64, material: Box<dyn Material>) -> Disk {
+        let normal = normal.normalize();
+        Disk {
+            center,
+            normal,
+            radius,
+            axes: normal.perpendiculars(),
+            material,
+        }
+    }
+}
+
+impl Hittable for Disk {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> HitOrMiss<'_> {
+        let denominator = self.normal.dot(&ray.direction);
+        // The ray runs along the plane of the disk.
+        if denominator.abs() < 1e-12 {
+            return HitOrMiss::Miss;
+        }
+        let t = self.normal.dot(&(self.center - ray.origin)) / denominator;
+        if t < t_min || t_max < t {
+            return HitOrMiss::Miss;
+        }
+        let p = ray.at(t);
+        let offset = p - self.center;
+        let distance = offset.magnitude();
+        if distance > self.radius {
+            return HitOrMiss::Miss;
+        }
+        let phi = offset.dot(&self.axes.1).atan2(offset.dot(&self.axes.0));
+        let uv = (
+            (phi + std::f64::consts::PI) / (2.0 * std::f64::consts::PI),
+            distance / self.radius,
+        );
+        HitOrMiss::hit(p, self.normal, t, uv, ray, &*self.material)
+    }
+
+    /// The box around the disk is only as wide along each axis as the disk reaches.
+    fn bounding_box(&self) -> Option<Aabb> {
+        let n = self.normal;
+        let extent = |component: f64| self.radius * (1.0 - component * component).max(0.0).sqrt();
+        let half = Vec3 {
+            x: extent(n.x),
+            y: extent(n.y),
+            z: extent(n.z),
+        };
+        Some(
+            Aabb {
+                min: self.center - half,
+                max: self.center + half,
+            }
+            .pad(1e-4),
+        )
+    }
+}
+
+#[test]
+fn test_ray_hits_disk_within_radius_only() {
+    use crate::material::lambertian::Lambertian;
+
+    // A disk of radius 2 on the ground, seen from above.
+    let up = Vec3 {
+        x: 0.0,
+        y: 1.0,
+        z: 0.0,
+    };
+    let disk = Disk::make(
+        Vec3::zero(),
+        up,
+        2.0,
+        Box::from(Lambertian::make(Vec3::from_one(0.5))),
+    );
+    let down_at = |x: f64, z: f64| Ray {
+        origin: Vec3 { x, y: 5.0, z },
+        direction: -up,
+        time: 0.0,
+    };
+
+    match disk.hit(&down_at(1.0, 0.0), 0.001, f64::INFINITY) {
+        HitOrMiss::Hit { t, point, .. } => {
+            assert!((t - 5.0).abs() < 1e-12);
+            assert!((point.v - 0.5).abs() < 1e-12);
+            assert!(point.front_face);
+            assert_eq!(up, point.normal);
+        }
+        HitOrMiss::Miss => panic!("Expected the ray to hit the disk"),
+    }
+    assert!(matches!(
+        disk.hit(&down_at(1.5, 1.5), 0.001, f64::INFINITY),
+        HitOrMiss::Miss
+    ));
+
+    // The bounding box is flat along the normal but covers the disk.
+    let bbox = disk.bounding_box().unwrap();
+    assert!(bbox.max.y < 0.01 && bbox.max.x >= 2.0 && bbox.min.z <= -2.0);
+}
@@ -0,0 +1,65 @@
+use std::sync::Arc;
+
+use crate::light::Light;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// Light from an image wrapped around the world.
+pub mod map;
+
+pub use map::EnvironmentMap;
+
+/// The light coming from a ray which escapes the scene without hitting anything.
+#[derive(Clone, Debug)]
+pub enum Environment {
+    /// The same color in every direction. Black for scenes lit only by their own lights.
+    Solid(Vec3),
+    /// A sky blending from the bottom color looking straight down to the top color looking
+    /// straight up.
+    Gradient {
+        /// The color looking straight down.
+        bottom: Vec3,
+        /// The color looking straight up.
+        top: Vec3,
+    },
+    /// An image of the light arriving from every direction. Shared, since the map can be large
+    /// and the environment is copied for each render.
+    Map(Arc<EnvironmentMap>),
+}
+
+impl Environment {
+    /// The white to blue sky from the book.
+    pub fn sky() -> Environment {
+        Environment::Gradient {
+            bottom: Vec3::from_one(1.0),
+            top: Vec3 {
+                x: 0.5,
+                y: 0.7,
+                z: 1.0,
+            },
+        }
+    }
+
+    /// The light arriving along a ray that escaped the scene.
+    pub fn color(&self, ray: &Ray) -> Vec3 {
+        match self {
+            Environment::Solid(color) => *color,
+            Environment::Gradient { bottom, top } => {
+                let unit_direction = ray.direction.normalize();
+                let t = 0.5 * (unit_direction.y + 1.0);
+                (1.0 - t) * *bottom + t * *top
+            }
+            Environment::Map(map) => map.color(ray.direction),
+        }
+    }
+
+    /// The environment as a Light to sample directly, if it is worth sampling. Solid colors and
+    /// gradients change so little with direction that following the materials finds them well,
+    /// but maps can hold small bright parts, like the sun, which only sampling finds reliably.
+    pub fn light(&self) -> Option<Box<dyn Light>> {
+        match self {
+            Environment::Map(map) => Some(Box::from(Arc::clone(map))),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,230 @@
+use std::f64::consts::PI;
+use std::fmt;
+
+use crate::image::Image;
+use crate::light::Light;
+use crate::sampler::Sampler;
+use crate::vec3::Vec3;
+
+/// An equirectangular (latitude-longitude) image of the light arriving from every direction,
+/// such as a photograph of the sky. The top row of the image looks straight up, the bottom row
+/// straight down, and the middle of the image looks along -z, which is where the camera looks
+/// in most scenes.
+///
+/// The map is also a Light, picking directions in proportion to how bright the image is that
+/// way, so that small bright parts like the sun are found by light sampling rather than left to
+/// rays that happen to escape towards them.
+pub struct EnvironmentMap {
+    image: Image,
+    /// The angle the map is turned by about the y axis, in radians.
+    rotation: f64,
+    intensity: f64,
+    /// How often each pixel is picked when sampling: its brightness times the solid angle it
+    /// covers, which shrinks towards the poles.
+    weights: Vec<f64>,
+    /// The running total of the weights of each row and those above it.
+    row_cdf: Vec<f64>,
+    /// The running total of the weights of each pixel and those to its left in the same row.
+    column_cdf: Vec<f64>,
+}
+
+impl EnvironmentMap {
+    /// Create a map from an image, turned anticlockwise seen from above by rotation degrees about
+    /// the y axis, with every color scaled by intensity.
+    pub fn make(image: Image, rotation: f64, intensity: f64) -> EnvironmentMap {
+        let (width, height) = (image.width, image.height);
+        let mut weights = Vec::with_capacity(width * height);
+        let mut row_cdf = Vec::with_capacity(height);
+        let mut column_cdf = Vec::with_capacity(width * height);
+        let mut total = 0.0;
+        for j in 0..height {
+            let sin_theta = (PI * (j as f64 + 0.5) / height as f64).sin();
+            let mut row_total = 0.0;
+            for i in 0..width {
+                let weight = luminance(image.get(i, j)).max(0.0) * sin_theta;
+                weights.push(weight);
+                row_total += weight;
+                column_cdf.push(row_total);
+            }
+            total += row_total;
+            row_cdf.push(total);
+        }
+        EnvironmentMap {
+            image,
+            rotation: rotation.to_radians(),
+            intensity,
+            weights,
+            row_cdf,
+            column_cdf,
+        }
+    }
+
+    /// The light arriving from the given direction.
+    pub fn color(&self, direction: Vec3) -> Vec3 {
+        let (i, j) = self.pixel(rotate_y(direction.normalize(), -self.rotation));
+        self.intensity * self.image.get(i, j)
+    }
+
+    /// The sum of the weights of every pixel.
+    fn total(&self) -> f64 {
+        self.row_cdf.last().copied().unwrap_or(0.0)
+    }
+
+    /// The pixel seen in a direction, given as a unit vector in the map's own frame.
+    fn pixel(&self, direction: Vec3) -> (usize, usize) {
+        let (u, v) = to_uv(direction);
+        let i = ((u * self.image.width as f64) as usize).min(self.image.width - 1);
+        let j = ((v * self.image.height as f64) as usize).min(self.image.height - 1);
+        (i, j)
+    }
+}
+
+impl fmt::Debug for EnvironmentMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EnvironmentMap")
+            .field("width", &self.image.width)
+            .field("height", &self.image.height)
+            .field("rotation", &self.rotation.to_degrees())
+            .field("intensity", &self.intensity)
+            .finish()
+    }
+}
+
+impl Light for EnvironmentMap {
+    /// Pick a pixel in proportion to its weight, first its row and then a pixel within the row,
+    /// and then a direction evenly from the part of the image the pixel covers.
+    fn sample(&self, _origin: Vec3, sampler: &mut Sampler) -> Option<Vec3> {
+        let total = self.total();
+        if total <= 0.0 {
+            return None;
+        }
+        let (width, height) = (self.image.width, self.image.height);
+        let target = sampler.random_double() * total;
+        let j = self
+            .row_cdf
+            .partition_point(|&c| c <= target)
+            .min(height - 1);
+        let row = &self.column_cdf[j * width..(j + 1) * width];
+        let target = sampler.random_double() * row[width - 1];
+        let i = row.partition_point(|&c| c <= target).min(width - 1);
+
+        let u = (i as f64 + sampler.random_double()) / width as f64;
+        let v = (j as f64 + sampler.random_double()) / height as f64;
+        Some(rotate_y(from_uv(u, v), self.rotation))
+    }
+
+    /// The chance of picking the pixel seen in the direction, spread over the solid angle it
+    /// covers. Pixels are even in u and v, and a small patch of the image du by dv covers a
+    /// solid angle of 2 pi^2 sin(theta) du dv.
+    fn pdf(&self, _origin: Vec3, direction: Vec3) -> f64 {
+        let total = self.total();
+        let direction = rotate_y(direction.normalize(), -self.rotation);
+        let sin_theta = (1.0 - direction.y * direction.y).max(0.0).sqrt();
+        if total <= 0.0 || sin_theta == 0.0 {
+            return 0.0;
+        }
+        let (i, j) = self.pixel(direction);
+        let probability = self.weights[j * self.image.width + i] / total;
+        let pixels = (self.image.width * self.image.height) as f64;
+        probability * pixels / (2.0 * PI * PI * sin_theta)
+    }
+}
+
+/// The brightness of a color as the eye sees it, from the Rec. 709 primaries.
+fn luminance(color: Vec3) -> f64 {
+    0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
+}
+
+/// Where a unit direction falls on the map, with u from 0 to 1 going round the horizon and v
+/// from 0 looking straight up to 1 looking straight down.
+fn to_uv(direction: Vec3) -> (f64, f64) {
+    let u = 0.5 + direction.x.atan2(-direction.z) / (2.0 * PI);
+    let v = direction.y.clamp(-1.0, 1.0).acos() / PI;
+    (u, v)
+}
+
+/// The inverse of to_uv.
+fn from_uv(u: f64, v: f64) -> Vec3 {
+    let phi = 2.0 * PI * (u - 0.5);
+    let theta = PI * v;
+    Vec3 {
+        x: theta.sin() * phi.sin(),
+        y: theta.cos(),
+        z: -theta.sin() * phi.cos(),
+    }
+}
+
+/// Turn a direction anticlockwise, seen from above, by angle radians about the y axis.
+fn rotate_y(direction: Vec3, angle: f64) -> Vec3 {
+    let (sin, cos) = angle.sin_cos();
+    Vec3 {
+        x: cos * direction.x + sin * direction.z,
+        y: direction.y,
+        z: -sin * direction.x + cos * direction.z,
+    }
+}
+
+#[cfg(test)]
+fn sun_map(rotation: f64) -> EnvironmentMap {
+    // A dim sky with a bright sun, and a black row at the bottom.
+    let mut image = Image::make(16, 8);
+    for j in 0..7 {
+        for i in 0..16 {
+            image.set(i, j, Vec3::from_one(0.1 + 0.05 * i as f64));
+        }
+    }
+    image.set(5, 2, Vec3::from_one(1000.0));
+    EnvironmentMap::make(image, rotation, 2.0)
+}
+
+#[test]
+fn test_environment_map_samples_consistently_with_its_pdf() {
+    let map = sun_map(30.0);
+    let mut sampler = Sampler::make(0);
+    let origin = Vec3::zero();
+
+    // The density over every direction adds up to one, estimated with directions picked evenly
+    // over the sphere, and the sun is picked about as often as its share of the weight.
+    let samples = 200000;
+    let total: f64 = (0..samples)
+        .map(|_| map.pdf(origin, Vec3::random_unit_vector(&mut sampler)))
+        .sum();
+    assert!((total * 4.0 * PI / samples as f64 - 1.0).abs() < 0.02);
+
+    let sun = rotate_y(from_uv(5.5 / 16.0, 2.5 / 8.0), map.rotation);
+    let sun_share = map.weights[2 * 16 + 5] / map.total();
+    let mut sun_samples = 0;
+    for _ in 0..10000 {
+        let direction = map.sample(origin, &mut sampler).unwrap();
+        assert!(map.pdf(origin, direction) > 0.0);
+        // Nothing is picked from the black row.
+        assert!(direction.y > -0.98);
+        if map.color(direction) == map.color(sun) {
+            sun_samples += 1;
+        }
+    }
+    assert!((sun_samples as f64 / 10000.0 - sun_share).abs() < 0.02);
+}
+
+#[test]
+fn test_environment_map_rotation_turns_the_image() {
+    let unturned = sun_map(0.0);
+    let turned = sun_map(90.0);
+    let sun = from_uv(5.5 / 16.0, 2.5 / 8.0);
+    assert_eq!(Vec3::from_one(2000.0), unturned.color(sun));
+    assert_eq!(
+        Vec3::from_one(2000.0),
+        turned.color(rotate_y(sun, PI / 2.0))
+    );
+    assert!(turned.color(sun).x < 10.0);
+    let pdf = unturned.pdf(Vec3::zero(), sun);
+    assert!((pdf - turned.pdf(Vec3::zero(), rotate_y(sun, PI / 2.0))).abs() < 1e-9);
+
+    // Turning anticlockwise seen from above takes -z, the middle of the image, towards -x.
+    let forward = Vec3 {
+        x: 0.0,
+        y: 0.0,
+        z: -1.0,
+    };
+    assert!((rotate_y(forward, PI / 2.0).x + 1.0).abs() < 1e-12);
+}
@@ -1,23 +1,54 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
 use crate::hit::HitOrMiss::{Hit, Miss};
-use crate::material::{Material, ScatterResult};
+use crate::light::Light;
+use crate::material::Material;
+use crate::medium::Medium;
 use crate::ray::Ray;
 use crate::vec3::Vec3;
 
-/// Whether a ray hit a Hittable, or missed.
-pub enum HitOrMiss {
+/// The point on a surface where a ray hit it, with everything a Material needs to know about the
+/// surface there.
+#[derive(Copy, Clone, Debug)]
+pub struct SurfacePoint {
+    /// Where the point is.
+    pub p: Vec3,
+    /// The surface normal, facing against the ray.
+    pub normal: Vec3,
+    /// Whether the ray hit the outside of the surface.
+    pub front_face: bool,
+    /// The first texture coordinate of the point.
+    pub u: f64,
+    /// The second texture coordinate of the point.
+    pub v: f64,
+}
+
+/// Whether a ray hit a Hittable, or missed. A hit records where along the ray it happened, the
+/// point on the surface that was hit, and the material of the surface there.
+pub enum HitOrMiss<'a> {
+    /// The ray hit something.
     Hit {
-        p: Vec3,
-        normal: Vec3,
-        scatter_result: ScatterResult,
+        /// How far along the ray the hit was, in multiples of the ray's direction.
         t: f64,
-        front_face: bool,
+        /// The point on the surface that was hit.
+        point: SurfacePoint,
+        /// The material of the surface.
+        material: &'a dyn Material,
+        /// Identifies the object that was hit, for the object ID AOV. Zero unless the object is
+        /// Labelled.
+        object_id: usize,
+        /// Identifies the material that was hit, for the material ID AOV. Zero unless the object
+        /// is Labelled.
+        material_id: usize,
     },
+    /// The ray hit nothing.
     Miss,
 }
 
-impl HitOrMiss {
+impl<'a> HitOrMiss<'a> {
     /// Create a HitOrMiss::Hit for a ray hitting an object at point p, with a given outward normal
-    /// at time step t.
+    /// at time step t. (u, v) are the texture coordinates of p on the object.
     ///
     /// This method handles detecting whether the ray is hitting the front face of the object or
     /// not.
@@ -25,40 +56,84 @@ impl HitOrMiss {
         p: Vec3,
         outward_normal: Vec3,
         t: f64,
+        (u, v): (f64, f64),
         ray: &Ray,
-        material: &dyn Material,
-    ) -> HitOrMiss {
+        material: &'a dyn Material,
+    ) -> HitOrMiss<'a> {
         let front_face = ray.direction.dot(&outward_normal) < 0.0;
         let normal = if front_face {
             outward_normal
         } else {
             -outward_normal
         };
-        let scatter_result = material.scatter(ray, normal, front_face);
         Hit {
-            p,
-            normal,
             t,
-            front_face,
-            scatter_result,
+            point: SurfacePoint {
+                p,
+                normal,
+                front_face,
+                u,
+                v,
+            },
+            material,
+            object_id: 0,
+            material_id: 0,
         }
     }
 }
 
 /// An object that might be hit by a ray.
-pub trait Hittable {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> HitOrMiss;
+///
+/// Hittables are shared between render threads, so must be Send and Sync.
+pub trait Hittable: Send + Sync {
+    /// Find the closest place the ray hits the object between t_min and t_max along it.
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> HitOrMiss<'_>;
+
+    /// The smallest axis-aligned box enclosing the Hittable, or None if it is unbounded.
+    fn bounding_box(&self) -> Option<Aabb>;
+
+    /// The parts of the Hittable that give off light, to be sampled as lights. Most objects have
+    /// none.
+    fn lights(&self) -> Vec<Box<dyn Light>> {
+        vec![]
+    }
+
+    /// The media, like fog, held by the Hittable, to be sampled along rays passing through them.
+    /// Most objects have none.
+    fn media(&self) -> Vec<Arc<dyn Medium>> {
+        vec![]
+    }
+}
+
+/// Shared Hittables, such as a mesh placed in the world several times by Instances.
+impl<H: Hittable + ?Sized> Hittable for Arc<H> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> HitOrMiss<'_> {
+        (**self).hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        (**self).bounding_box()
+    }
+
+    fn lights(&self) -> Vec<Box<dyn Light>> {
+        (**self).lights()
+    }
+
+    fn media(&self) -> Vec<Arc<dyn Medium>> {
+        (**self).media()
+    }
 }
 
 /// A List of Hittable structs that is itself Hittable.
 pub struct HittableList {
+    /// The objects in the list.
     pub hittables: Vec<Box<dyn Hittable>>,
 }
 
 impl Hittable for HittableList {
     /// Test whether the ray hits any object in the HittableList. Returns a HitOrMiss::Hit
     /// containing the details of the hit if it did. Otherwise returns a HitOrMiss::Miss.
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> HitOrMiss {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> HitOrMiss<'_> {
         // Start assuming we are missing.
         let mut hit_or_miss = Miss;
         // Iterate over all of the hittable objects
@@ -79,4 +154,67 @@ impl Hittable for HittableList {
         }
         hit_or_miss
     }
+
+    /// The box surrounding every object in the list. None if the list is empty, or any object
+    /// is unbounded.
+    fn bounding_box(&self) -> Option<Aabb> {
+        let mut hittables = self.hittables.iter();
+        let first = hittables.next()?.bounding_box()?;
+        hittables.try_fold(first, |bbox, hittable| {
+            Some(Aabb::surrounding(&bbox, &hittable.bounding_box()?))
+        })
+    }
+
+    fn lights(&self) -> Vec<Box<dyn Light>> {
+        self.hittables.iter().flat_map(|hittable| hittable.lights()).collect()
+    }
+
+    fn media(&self) -> Vec<Arc<dyn Medium>> {
+        self.hittables.iter().flat_map(|hittable| hittable.media()).collect()
+    }
+}
+
+/// A Hittable labelled with IDs for the object ID and material ID AOVs.
+pub struct Labelled {
+    /// The labelled object.
+    pub hittable: Box<dyn Hittable>,
+    /// The ID given to every hit on the object.
+    pub object_id: usize,
+    /// Added to the material ID of every hit on the object. Objects made of a single material
+    /// report zero, so this is their material's ID, while meshes report the index of each face's
+    /// material, so this is the ID of the mesh's first material.
+    pub material_id: usize,
+}
+
+impl Hittable for Labelled {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> HitOrMiss<'_> {
+        match self.hittable.hit(ray, t_min, t_max) {
+            Hit {
+                t,
+                point,
+                material,
+                material_id,
+                ..
+            } => Hit {
+                t,
+                point,
+                material,
+                object_id: self.object_id,
+                material_id: self.material_id + material_id,
+            },
+            Miss => Miss,
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.hittable.bounding_box()
+    }
+
+    fn lights(&self) -> Vec<Box<dyn Light>> {
+        self.hittable.lights()
+    }
+
+    fn media(&self) -> Vec<Arc<dyn Medium>> {
+        self.hittable.media()
+    }
 }
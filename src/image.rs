@@ -0,0 +1,203 @@
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::BufReader;
+use std::path::Path;
+
+use crate::vec3::Vec3;
+
+/// An in-memory framebuffer holding the averaged color of every pixel of a render.
+///
+/// Colors are linear radiance, unclamped, exactly as the renderer produced them. HDR formats
+/// write them out as they are, while 8 bit formats and the window first turn them into display
+/// colors with to_rgb8 or vec_to_color.
+///
+/// Pixels are stored row by row, starting from the top left of the image.
+pub struct Image {
+    /// The width of the image in pixels.
+    pub width: usize,
+    /// The height of the image in pixels.
+    pub height: usize,
+    pixels: Vec<Vec3>,
+}
+
+impl Image {
+    /// Create a black image of the given dimensions.
+    pub fn make(width: usize, height: usize) -> Image {
+        Image {
+            width,
+            height,
+            pixels: vec![Vec3::zero(); width * height],
+        }
+    }
+
+    /// Load a PNG or binary PPM file, converting the 8 bit colors back to linear colors so the
+    /// image can be used as a texture, or an OpenEXR or Radiance HDR file, whose colors are
+    /// already linear.
+    pub fn load(path: &Path) -> io::Result<Image> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        let image = match extension.as_deref() {
+            Some("png") => from_rgb8(load_png(path)?),
+            Some("ppm") => from_rgb8(load_ppm(path)?),
+            Some("exr") => crate::output::exr::read(path)?,
+            Some("hdr") => crate::output::hdr::read(path)?,
+            _ => {
+                return Err(invalid_data(format!(
+                    "{} is not a .png, .ppm, .exr or .hdr file",
+                    path.display()
+                )))
+            }
+        };
+        if image.width == 0 || image.height == 0 {
+            return Err(invalid_data(format!("{} has no pixels", path.display())));
+        }
+        Ok(image)
+    }
+
+    /// The color of the pixel in column i and row j, counting rows from the top.
+    pub fn get(&self, i: usize, j: usize) -> Vec3 {
+        self.pixels[j * self.width + i]
+    }
+
+    /// Change the color of the pixel in column i and row j.
+    pub fn set(&mut self, i: usize, j: usize, color: Vec3) {
+        self.pixels[j * self.width + i] = color;
+    }
+
+    /// Convert the image to 8 bit display RGB triples, row by row, ready to be written out. See
+    /// vec_to_color.
+    pub fn to_rgb8(&self) -> Vec<u8> {
+        self.pixels.iter().flat_map(|p| vec_to_color(*p)).collect()
+    }
+}
+
+/// Turn 8 bit RGB triples, given with the width and height of the image, into linear colors.
+fn from_rgb8((width, height, rgb): (usize, usize, Vec<u8>)) -> Image {
+    let mut image = Image::make(width, height);
+    for (pixel, rgb) in image.pixels.iter_mut().zip(rgb.chunks_exact(3)) {
+        *pixel = color_to_vec(rgb[0], rgb[1], rgb[2]);
+    }
+    image
+}
+
+/// The display transform: gamma correct a linear color, clamp it to [0.0, 1.0] and quantise it
+/// to 8 bit RGB. Anything brighter than white is lost.
+pub fn vec_to_color(color_vec: Vec3) -> [u8; 3] {
+    [
+        (color_vec.x.sqrt().clamp(0.0, 0.999) * 256.0) as u8,
+        (color_vec.y.sqrt().clamp(0.0, 0.999) * 256.0) as u8,
+        (color_vec.z.sqrt().clamp(0.0, 0.999) * 256.0) as u8,
+    ]
+}
+
+/// The inverse of vec_to_color.
+fn color_to_vec(r: u8, g: u8, b: u8) -> Vec3 {
+    let linear = |c: u8| (c as f64 / 255.0).powi(2);
+    Vec3 {
+        x: linear(r),
+        y: linear(g),
+        z: linear(b),
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Read a PNG file as 8 bit RGB triples.
+fn load_png(path: &Path) -> io::Result<(usize, usize, Vec<u8>)> {
+    let mut decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+    // Expand palettes and low bit depths, and strip 16 bit depths, so we always get 8 bits.
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder
+        .read_info()
+        .map_err(|e| invalid_data(e.to_string()))?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut buffer)
+        .map_err(|e| invalid_data(e.to_string()))?;
+    let bytes = &buffer[..info.buffer_size()];
+    let rgb = match info.color_type {
+        png::ColorType::Rgb => bytes.to_vec(),
+        png::ColorType::Rgba => bytes
+            .chunks_exact(4)
+            .flat_map(|p| [p[0], p[1], p[2]])
+            .collect(),
+        png::ColorType::Grayscale => bytes.iter().flat_map(|&g| [g, g, g]).collect(),
+        png::ColorType::GrayscaleAlpha => bytes
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0]])
+            .collect(),
+        png::ColorType::Indexed => unreachable!("Palettes are expanded by the decoder"),
+    };
+    Ok((info.width as usize, info.height as usize, rgb))
+}
+
+/// Read a binary (P6) PPM file with 8 bit colors, as written by output::ppm.
+fn load_ppm(path: &Path) -> io::Result<(usize, usize, Vec<u8>)> {
+    let bytes = fs::read(path)?;
+    // The header is four whitespace separated fields, followed by a single whitespace character.
+    let mut fields = vec![];
+    let mut position = 0;
+    while fields.len() < 4 {
+        while position < bytes.len() && bytes[position].is_ascii_whitespace() {
+            position += 1;
+        }
+        if bytes.get(position) == Some(&b'#') {
+            while position < bytes.len() && bytes[position] != b'\n' {
+                position += 1;
+            }
+            continue;
+        }
+        let start = position;
+        while position < bytes.len() && !bytes[position].is_ascii_whitespace() {
+            position += 1;
+        }
+        if start == position {
+            return Err(invalid_data(format!(
+                "{} has an incomplete header",
+                path.display()
+            )));
+        }
+        fields.push(String::from_utf8_lossy(&bytes[start..position]).to_string());
+    }
+    let number = |field: &str| {
+        field
+            .parse::<usize>()
+            .map_err(|_| invalid_data(format!("{} has an invalid header", path.display())))
+    };
+    if fields[0] != "P6" || number(&fields[3])? != 255 {
+        return Err(invalid_data(format!(
+            "{} is not a binary PPM with 8 bit colors",
+            path.display()
+        )));
+    }
+    let (width, height) = (number(&fields[1])?, number(&fields[2])?);
+    // The size comes from the file, so may be too large to count the bytes of.
+    let size = width
+        .checked_mul(height)
+        .and_then(|n| n.checked_mul(3))
+        .filter(|&size| size > 0)
+        .ok_or_else(|| invalid_data(format!("{} has an invalid size", path.display())))?;
+    let data = &bytes[(position + 1).min(bytes.len())..];
+    if data.len() < size {
+        return Err(invalid_data(format!("{} is truncated", path.display())));
+    }
+    Ok((width, height, data[..size].to_vec()))
+}
+
+#[test]
+fn test_ppm_with_an_impossible_size_is_rejected() {
+    let path = std::env::temp_dir().join("rays_test_ppm_with_an_impossible_size_is_rejected.ppm");
+    for header in ["P6 0 2 255\n", "P6 4294967296 4294967296 255\n"] {
+        fs::write(&path, [header.as_bytes(), &[0; 12]].concat()).unwrap();
+        let error = load_ppm(&path).err().unwrap();
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
+    }
+    fs::write(&path, [&b"P6 2 2 255\n"[..], &[0; 12]].concat()).unwrap();
+    assert_eq!(12, load_ppm(&path).unwrap().2.len());
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,247 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hit::{HitOrMiss, Hittable};
+use crate::light::Light;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::transform::{Motion, Transform};
+use crate::vec3::Vec3;
+
+/// An object moved, turned or stretched into place by a Transform. The object is shared rather
+/// than copied, so one mesh can be placed thousands of times for little more memory than once.
+///
+/// Rays are transformed into the object's own space to be tested against it, and the points and
+/// normals they hit are transformed back out. Directions aren't normalized on the way in, so
+/// distances along rays are the same in both spaces.
+///
+/// An instance may also move while the shutter is open, with its transform following a Motion.
+/// Moving instances that give off light aren't sampled as lights, since lights are the same at
+/// every moment.
+pub struct Instance {
+    object: Arc<dyn Hittable>,
+    transform: Transform,
+    motion: Option<Motion>,
+    bbox: Option<Aabb>,
+}
+
+impl Instance {
+    /// Place an object in the world with a transform from its own space.
+    pub fn make(object: Arc<dyn Hittable>, transform: Transform) -> Instance {
+        let bbox = object
+            .bounding_box()
+            .map(|bbox| transform.bounding_box(&bbox));
+        Instance {
+            object,
+            transform,
+            motion: None,
+            bbox,
+        }
+    }
+
+    /// Place an object in the world with a transform from its own space that changes over time.
+    pub fn moving(object: Arc<dyn Hittable>, motion: Motion) -> Instance {
+        let bbox = object.bounding_box().map(|bbox| motion.bounding_box(&bbox));
+        Instance {
+            object,
+            transform: motion.at(0.0),
+            motion: Some(motion),
+            bbox,
+        }
+    }
+}
+
+impl Hittable for Instance {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> HitOrMiss<'_> {
+        let transform = match &self.motion {
+            Some(motion) => motion.at(ray.time),
+            None => self.transform,
+        };
+        let inverse = transform.inverse();
+        let object_ray = Ray {
+            origin: inverse.point(ray.origin),
+            direction: inverse.vector(ray.direction),
+            time: ray.time,
+        };
+        match self.object.hit(&object_ray, t_min, t_max) {
+            HitOrMiss::Hit {
+                t,
+                mut point,
+                material,
+                object_id,
+                material_id,
+            } => {
+                // The normal still faces against the ray, as transforming both keeps the sign of
+                // their dot product.
+                point.p = transform.point(point.p);
+                point.normal = transform.normal(point.normal).normalize();
+                HitOrMiss::Hit {
+                    t,
+                    point,
+                    material,
+                    object_id,
+                    material_id,
+                }
+            }
+            HitOrMiss::Miss => HitOrMiss::Miss,
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bbox
+    }
+
+    fn lights(&self) -> Vec<Box<dyn Light>> {
+        if self.motion.is_some() {
+            return vec![];
+        }
+        self.object
+            .lights()
+            .into_iter()
+            .map(|light| {
+                Box::from(TransformedLight {
+                    light,
+                    transform: self.transform,
+                }) as Box<dyn Light>
+            })
+            .collect()
+    }
+}
+
+/// A light in an Instance, sampled in the instanced object's own space.
+pub struct TransformedLight {
+    /// The light in the object's space.
+    pub light: Box<dyn Light>,
+    /// The transform from the object's space into the world.
+    pub transform: Transform,
+}
+
+impl Light for TransformedLight {
+    fn sample(&self, origin: Vec3, sampler: &mut Sampler) -> Option<Vec3> {
+        let direction = self
+            .light
+            .sample(self.transform.inverse().point(origin), sampler)?;
+        Some(self.transform.vector(direction))
+    }
+
+    /// The density in the object's space, corrected for how the transform stretches solid
+    /// angles. A small cone of directions d in the object's space covers |det M| / |M d|^3 times
+    /// the solid angle in the world.
+    fn pdf(&self, origin: Vec3, direction: Vec3) -> f64 {
+        let inverse = self.transform.inverse();
+        let object_direction = inverse.vector(direction.normalize());
+        let pdf = self.light.pdf(inverse.point(origin), object_direction);
+        let stretch = object_direction.magnitude();
+        pdf / (self.transform.determinant().abs() * stretch * stretch * stretch)
+    }
+}
+
+#[test]
+fn test_instance_of_unit_sphere_hits_like_a_moved_sphere() {
+    use crate::material::lambertian::Lambertian;
+    use crate::sphere::Sphere;
+
+    let unit = || Sphere {
+        center: Vec3::zero(),
+        r: 1.0,
+        material: Box::from(Lambertian::make(Vec3::from_one(0.5))),
+    };
+    let center = Vec3 {
+        x: 1.0,
+        y: -2.0,
+        z: 3.0,
+    };
+    let instance = Instance::make(
+        Arc::new(unit()),
+        Transform::scale(Vec3::from_one(2.0))
+            .then(&Transform::rotate_z(30.0))
+            .then(&Transform::translate(center)),
+    );
+    let sphere = Sphere {
+        center,
+        r: 2.0,
+        ..unit()
+    };
+    // The box around the turned box of the sphere holds the sphere's own box.
+    let (bbox, sphere_box) = (
+        instance.bounding_box().unwrap(),
+        sphere.bounding_box().unwrap(),
+    );
+    for axis in 0..3 {
+        assert!(bbox.min[axis] <= sphere_box.min[axis] + 1e-9);
+        assert!(bbox.max[axis] >= sphere_box.max[axis] - 1e-9);
+    }
+
+    let mut sampler = Sampler::make(0);
+    let mut hits = 0;
+    for _ in 0..1000 {
+        let ray = Ray {
+            origin: Vec3::random(&mut sampler, -1.0, 1.0) + Vec3::from_one(-5.0),
+            direction: center + Vec3::random(&mut sampler, -3.0, 3.0) - Vec3::from_one(-5.0),
+            time: 0.0,
+        };
+        match (
+            sphere.hit(&ray, 0.001, f64::INFINITY),
+            instance.hit(&ray, 0.001, f64::INFINITY),
+        ) {
+            (
+                HitOrMiss::Hit { t, point, .. },
+                HitOrMiss::Hit {
+                    t: instance_t,
+                    point: instance_point,
+                    ..
+                },
+            ) => {
+                hits += 1;
+                assert!((t - instance_t).abs() < 1e-9);
+                assert!((point.p - instance_point.p).magnitude() < 1e-9);
+                assert!((point.normal - instance_point.normal).magnitude() < 1e-9);
+                assert_eq!(point.front_face, instance_point.front_face);
+            }
+            (HitOrMiss::Miss, HitOrMiss::Miss) => {}
+            _ => panic!("The instance and sphere disagree about {:?}", ray.direction),
+        }
+    }
+    assert!(hits > 100);
+}
+
+#[test]
+fn test_stretched_light_samples_consistently_with_its_pdf() {
+    use std::f64::consts::PI;
+
+    use crate::light::SphereLight;
+
+    // A sphere light stretched into an ellipsoid and turned, seen from nearby.
+    let light = TransformedLight {
+        light: Box::from(SphereLight {
+            center: Vec3::zero(),
+            r: 1.0,
+        }),
+        transform: Transform::scale(Vec3 {
+            x: 3.0,
+            y: 0.5,
+            z: 1.0,
+        })
+        .then(&Transform::rotate_y(40.0))
+        .then(&Transform::translate(Vec3 {
+            x: 0.0,
+            y: 2.0,
+            z: 0.0,
+        })),
+    };
+    let origin = Vec3 {
+        x: 0.5,
+        y: 0.0,
+        z: 0.3,
+    };
+    let mut sampler = Sampler::make(0);
+    for _ in 0..1000 {
+        let direction = light.sample(origin, &mut sampler).unwrap();
+        assert!(light.pdf(origin, direction) > 0.0);
+    }
+    let samples = 400000;
+    let total: f64 = (0..samples)
+        .map(|_| light.pdf(origin, Vec3::random_unit_vector(&mut sampler)))
+        .sum();
+    assert!((total * 4.0 * PI / samples as f64 - 1.0).abs() < 0.02);
+}
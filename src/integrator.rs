@@ -0,0 +1,170 @@
+use std::sync::Arc;
+
+use clap::ValueEnum;
+
+use crate::environment::Environment;
+use crate::hit::{HitOrMiss, Hittable};
+use crate::light::LightList;
+use crate::medium::Medium;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::vec3::Vec3;
+
+/// Path tracing with light sampling.
+pub mod path;
+/// Path tracing by following the materials alone.
+pub mod random_walk;
+
+pub use path::PathTracer;
+pub use random_walk::RandomWalk;
+
+/// The bounce after which paths may be ended early with Russian roulette, unless another is
+/// chosen.
+pub const ROULETTE_DEPTH: usize = 3;
+
+/// Everything rays are traced through: the objects, the lights and media among them and the
+/// environment.
+pub struct World<'a> {
+    /// The objects in the world.
+    pub hittable: &'a dyn Hittable,
+    /// The parts of the objects that give off light, and the environment if it is sampled.
+    pub lights: LightList,
+    /// The media, like fog, that rays may be scattered by on their way between surfaces.
+    pub media: Vec<Arc<dyn Medium>>,
+    /// The light arriving from rays which hit nothing.
+    pub environment: &'a Environment,
+}
+
+impl<'a> World<'a> {
+    /// A world of the given objects, finding the lights among them and in the environment, and
+    /// the media among them.
+    pub fn make(hittable: &'a dyn Hittable, environment: &'a Environment) -> World<'a> {
+        let mut lights = hittable.lights();
+        lights.extend(environment.light());
+        World {
+            hittable,
+            lights: LightList { lights },
+            media: hittable.media(),
+            environment,
+        }
+    }
+
+    /// Find where the ray is next scattered: at the first surface it hits, or before that by one
+    /// of the media it passes through.
+    ///
+    /// Each medium is only sampled up to the closest scattering found so far. The first of
+    /// several media to scatter the ray is the one that does, so overlapping media add up.
+    pub fn hit(&self, ray: &Ray, sampler: &mut Sampler) -> HitOrMiss<'_> {
+        let mut closest = self.hittable.hit(ray, 0.001, f64::INFINITY);
+        for medium in &self.media {
+            let t_max = match closest {
+                HitOrMiss::Hit { t, .. } => t,
+                HitOrMiss::Miss => f64::INFINITY,
+            };
+            let scattering = medium.sample(ray, 0.001, t_max, sampler);
+            if let HitOrMiss::Hit { .. } = scattering {
+                closest = scattering;
+            }
+        }
+        closest
+    }
+
+    /// The fraction of light travelling along the ray up to t_max that makes it through every
+    /// medium on the way, for shadow rays.
+    pub fn transmittance(&self, ray: &Ray, t_max: f64, sampler: &mut Sampler) -> f64 {
+        self.media
+            .iter()
+            .map(|medium| medium.transmittance(ray, 0.001, t_max, sampler))
+            .product()
+    }
+}
+
+/// A way of working out how much light arrives along a ray, by following it as it bounces
+/// around the world.
+///
+/// Integrators are shared between render threads, so must be Send and Sync.
+pub trait Integrator: Send + Sync {
+    /// The light arriving along the ray, drawing any random numbers needed from the sampler.
+    fn radiance(&self, ray: &Ray, world: &World, sampler: &mut Sampler) -> Vec3;
+}
+
+/// The integrators that can be chosen from the command line.
+#[derive(ValueEnum, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IntegratorKind {
+    /// Path tracing, sampling the lights at each matte surface as well as following the
+    /// materials, and combining the two with multiple importance sampling.
+    Path,
+    /// Path tracing by following the materials alone, as in the book. Much noisier for small
+    /// lights, but handy for checking the path tracer.
+    RandomWalk,
+}
+
+impl IntegratorKind {
+    /// Create an integrator of this kind, following paths for at most max_depth rays and ending
+    /// them with Russian roulette from roulette_depth bounces on.
+    pub fn make(&self, max_depth: usize, roulette_depth: usize) -> Box<dyn Integrator> {
+        match self {
+            IntegratorKind::Path => Box::from(PathTracer::make(max_depth, roulette_depth)),
+            IntegratorKind::RandomWalk => Box::from(RandomWalk::make(max_depth, roulette_depth)),
+        }
+    }
+}
+
+/// Decide with Russian roulette whether a path carrying the given throughput carries on.
+/// Paths carrying less light are more likely to be ended. Returns the throughput of a path that
+/// survives, scaled up to make up for the paths that were ended, or None if it was ended.
+fn russian_roulette(throughput: Vec3, sampler: &mut Sampler) -> Option<Vec3> {
+    let survival = throughput.x.max(throughput.y).max(throughput.z).min(0.95);
+    if sampler.random_double() < survival {
+        Some(throughput / survival)
+    } else {
+        None
+    }
+}
+
+#[test]
+fn test_russian_roulette_is_unbiased() {
+    use crate::hit::HittableList;
+    use crate::material::UniformScatterer;
+    use crate::sphere::Sphere;
+
+    // Light bounces off a grey ball exactly once on its way from a white sky, so every path
+    // carries back exactly the albedo without Russian roulette, and the same on average with it.
+    let albedo = 0.5;
+    let hittable = HittableList {
+        hittables: vec![Box::from(Sphere {
+            center: Vec3::zero(),
+            r: 1.0,
+            material: Box::from(UniformScatterer::make(Vec3::from_one(albedo))),
+        })],
+    };
+    let environment = Environment::Solid(Vec3::from_one(1.0));
+    let world = World::make(&hittable, &environment);
+    let ray = Ray {
+        origin: Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: 5.0,
+        },
+        direction: Vec3 {
+            x: 0.05,
+            y: 0.1,
+            z: -1.0,
+        },
+        time: 0.0,
+    };
+
+    for kind in [IntegratorKind::Path, IntegratorKind::RandomWalk] {
+        let samples = 20000;
+        let mean = |roulette_depth| {
+            let integrator = kind.make(10, roulette_depth);
+            let total = (0..samples).fold(Vec3::zero(), |total, sample| {
+                let mut sampler = Sampler::for_sample(0, 0, 0, sample);
+                total + integrator.radiance(&ray, &world, &mut sampler)
+            });
+            total.x / samples as f64
+        };
+        assert_eq!(albedo, mean(10));
+        assert!((mean(0) - albedo).abs() < 0.02);
+    }
+}
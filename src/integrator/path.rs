@@ -0,0 +1,275 @@
+use crate::hit::{HitOrMiss, SurfacePoint};
+use crate::integrator::{russian_roulette, Integrator, World};
+use crate::light::Light;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::vec3::Vec3;
+
+/// A path tracer which, at each matte surface, samples the lights directly through a shadow ray
+/// as well as following the material to the next bounce. Either may find the same light, so the
+/// two are combined with multiple importance sampling. Small lights are found far more often
+/// than by following the materials alone.
+///
+/// Paths are followed in a loop rather than by recursion, so very deep paths can't overflow the
+/// stack.
+pub struct PathTracer {
+    max_depth: usize,
+    roulette_depth: usize,
+}
+
+impl PathTracer {
+    /// Create a path tracer following paths for at most max_depth rays, and ending them with
+    /// Russian roulette from roulette_depth bounces on.
+    pub fn make(max_depth: usize, roulette_depth: usize) -> PathTracer {
+        PathTracer {
+            max_depth,
+            roulette_depth,
+        }
+    }
+}
+
+impl Integrator for PathTracer {
+    fn radiance(&self, ray: &Ray, world: &World, sampler: &mut Sampler) -> Vec3 {
+        let mut color = Vec3::zero();
+        // How much of the light arriving along the current ray makes it back to the camera.
+        let mut throughput = Vec3::from_one(1.0);
+        let mut ray = Ray {
+            origin: ray.origin,
+            direction: ray.direction,
+            time: ray.time,
+        };
+        // The density with which the material the ray bounced off picked its direction, or None
+        // for rays from the camera, and rays off the delta lobes of materials like mirrors and
+        // glass, which light sampling is not used for.
+        let mut scatter_pdf = None;
+
+        for bounce in 0..self.max_depth {
+            let (point, material) = match world.hit(&ray, sampler) {
+                HitOrMiss::Hit {
+                    point, material, ..
+                } => (point, material),
+                HitOrMiss::Miss => {
+                    // An environment map may also have been found by sampling the lights.
+                    let mut background = world.environment.color(&ray);
+                    if let Some(scatter_pdf) = scatter_pdf {
+                        let light_pdf = world.lights.pdf(ray.origin, ray.direction);
+                        background = power_heuristic(scatter_pdf, light_pdf) * background;
+                    }
+                    color = color + throughput * background;
+                    break;
+                }
+            };
+
+            // Light given off by the object itself. If this is a light, the last bounce may also
+            // have found it by sampling the lights, so it only counts for its share. Glowing
+            // media aren't lights, so count in full.
+            let mut emitted = material.emitted(&point);
+            if let Some(scatter_pdf) = scatter_pdf.filter(|_| material.emits_light()) {
+                let light_pdf = world.lights.pdf(ray.origin, ray.direction);
+                emitted = power_heuristic(scatter_pdf, light_pdf) * emitted;
+            }
+            color = color + throughput * emitted;
+
+            let sample = match material.sample(&ray, &point, sampler) {
+                Some(sample) => sample,
+                None => break,
+            };
+            // Sample the lights directly, unless the material picked a delta lobe, which lights
+            // can't be found through, or there is no bounce left to also find them by
+            // scattering.
+            scatter_pdf = if !sample.delta && bounce + 1 < self.max_depth {
+                color = color + throughput * direct_light(&ray, &point, material, world, sampler);
+                Some(sample.pdf)
+            } else {
+                None
+            };
+
+            throughput = throughput * sample.weight;
+            if bounce + 1 >= self.roulette_depth {
+                throughput = match russian_roulette(throughput, sampler) {
+                    Some(throughput) => throughput,
+                    None => break,
+                };
+            }
+            ray = Ray {
+                origin: point.p,
+                direction: sample.direction,
+                time: ray.time,
+            };
+        }
+        color
+    }
+}
+
+/// The weight given to a sample found one way when it could also have been found another, so
+/// that each way counts most where it is better at finding light. This is the power heuristic
+/// for multiple importance sampling, from Eric Veach's thesis.
+fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    if a + b == 0.0 {
+        0.0
+    } else {
+        a / (a + b)
+    }
+}
+
+/// The light reaching a point straight from a light picked at random, through a shadow ray
+/// towards it, and reflected back along the ray by the material there. It is weighted against
+/// the chance of the material scattering towards the same light.
+fn direct_light(
+    ray: &Ray,
+    point: &SurfacePoint,
+    material: &dyn Material,
+    world: &World,
+    sampler: &mut Sampler,
+) -> Vec3 {
+    let direction = match world.lights.sample(point.p, sampler) {
+        Some(direction) => direction,
+        None => return Vec3::zero(),
+    };
+    let scatter_pdf = material.pdf(ray, point, direction);
+    let light_pdf = world.lights.pdf(point.p, direction);
+    // The light is behind the surface, or only just grazed.
+    if scatter_pdf == 0.0 || light_pdf == 0.0 {
+        return Vec3::zero();
+    }
+    let shadow_ray = Ray {
+        origin: point.p,
+        direction,
+        time: ray.time,
+    };
+    // Whatever the shadow ray hits first, which only gives off light if it is a light, or the
+    // environment if it escapes, dimmed by any media on the way.
+    let incoming = match world.hittable.hit(&shadow_ray, 0.001, f64::INFINITY) {
+        HitOrMiss::Hit {
+            t,
+            point: light_point,
+            material: light_material,
+            ..
+        } => world.transmittance(&shadow_ray, t, sampler) * light_material.emitted(&light_point),
+        HitOrMiss::Miss => {
+            world.transmittance(&shadow_ray, f64::INFINITY, sampler)
+                * world.environment.color(&shadow_ray)
+        }
+    };
+    let weight = power_heuristic(light_pdf, scatter_pdf);
+    weight / light_pdf * material.eval(ray, point, direction) * incoming
+}
+
+#[test]
+fn test_small_light_converges_to_its_exact_illumination() {
+    use crate::environment::Environment;
+    use crate::hit::HittableList;
+    use crate::integrator::ROULETTE_DEPTH;
+    use crate::material::{DiffuseLight, Lambertian};
+    use crate::sphere::Sphere;
+
+    // A matte floor with a small light a height h straight above the origin. The light reflected
+    // from the origin is exactly albedo * light * (r / h)^2.
+    let (albedo, light, r, h) = (0.5, 50.0, 0.1, 1.0);
+    let hittable = HittableList {
+        hittables: vec![
+            Box::from(Sphere {
+                center: Vec3 {
+                    x: 0.0,
+                    y: -1000.0,
+                    z: 0.0,
+                },
+                r: 1000.0,
+                material: Box::from(Lambertian::make(Vec3::from_one(albedo))),
+            }),
+            Box::from(Sphere {
+                center: Vec3 {
+                    x: 0.0,
+                    y: h,
+                    z: 0.0,
+                },
+                r,
+                material: Box::from(DiffuseLight::make(Vec3::from_one(light))),
+            }),
+        ],
+    };
+    let environment = Environment::Solid(Vec3::zero());
+    let world = World::make(&hittable, &environment);
+    let ray = Ray {
+        origin: Vec3 {
+            x: 2.0,
+            y: 2.0,
+            z: 0.0,
+        },
+        direction: Vec3 {
+            x: -1.0,
+            y: -1.0,
+            z: 0.0,
+        },
+        time: 0.0,
+    };
+
+    let integrator = PathTracer::make(10, ROULETTE_DEPTH);
+    let samples = 1000;
+    let total = (0..samples).fold(Vec3::zero(), |total, sample| {
+        let mut sampler = Sampler::for_sample(0, 0, 0, sample);
+        total + integrator.radiance(&ray, &world, &mut sampler)
+    });
+    let expected = albedo * light * (r / h) * (r / h);
+    assert!((total.x / samples as f64 - expected).abs() < 0.02 * expected);
+}
+
+#[test]
+fn test_environment_map_lighting_converges_to_its_exact_illumination() {
+    use std::sync::Arc;
+
+    use crate::environment::{Environment, EnvironmentMap};
+    use crate::hit::HittableList;
+    use crate::image::Image;
+    use crate::integrator::ROULETTE_DEPTH;
+    use crate::material::Lambertian;
+    use crate::sphere::Sphere;
+
+    // A matte floor under a map giving the same light from every direction, which is sampled
+    // as a light. The light reflected from the floor is exactly albedo * light.
+    let (albedo, light) = (0.5, 2.0);
+    let hittable = HittableList {
+        hittables: vec![Box::from(Sphere {
+            center: Vec3 {
+                x: 0.0,
+                y: -1000.0,
+                z: 0.0,
+            },
+            r: 1000.0,
+            material: Box::from(Lambertian::make(Vec3::from_one(albedo))),
+        })],
+    };
+    let mut image = Image::make(8, 4);
+    for j in 0..4 {
+        for i in 0..8 {
+            image.set(i, j, Vec3::from_one(1.0));
+        }
+    }
+    let environment = Environment::Map(Arc::new(EnvironmentMap::make(image, 0.0, light)));
+    let world = World::make(&hittable, &environment);
+    assert_eq!(1, world.lights.lights.len());
+    let ray = Ray {
+        origin: Vec3 {
+            x: 2.0,
+            y: 2.0,
+            z: 0.0,
+        },
+        direction: Vec3 {
+            x: -1.0,
+            y: -1.0,
+            z: 0.0,
+        },
+        time: 0.0,
+    };
+
+    let integrator = PathTracer::make(10, ROULETTE_DEPTH);
+    let samples = 1000;
+    let total = (0..samples).fold(Vec3::zero(), |total, sample| {
+        let mut sampler = Sampler::for_sample(0, 0, 0, sample);
+        total + integrator.radiance(&ray, &world, &mut sampler)
+    });
+    let expected = albedo * light;
+    assert!((total.x / samples as f64 - expected).abs() < 0.02 * expected);
+}
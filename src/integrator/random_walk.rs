@@ -0,0 +1,68 @@
+use crate::hit::HitOrMiss;
+use crate::integrator::{russian_roulette, Integrator, World};
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::vec3::Vec3;
+
+/// A path tracer which only follows the directions the materials pick, as in the book, so
+/// lights are only found when a path happens to bounce into one. Much noisier than the
+/// PathTracer for small lights, but simple enough to check it against.
+pub struct RandomWalk {
+    max_depth: usize,
+    roulette_depth: usize,
+}
+
+impl RandomWalk {
+    /// Create a random walk following paths for at most max_depth rays, and ending them with
+    /// Russian roulette from roulette_depth bounces on.
+    pub fn make(max_depth: usize, roulette_depth: usize) -> RandomWalk {
+        RandomWalk {
+            max_depth,
+            roulette_depth,
+        }
+    }
+}
+
+impl Integrator for RandomWalk {
+    fn radiance(&self, ray: &Ray, world: &World, sampler: &mut Sampler) -> Vec3 {
+        let mut color = Vec3::zero();
+        // How much of the light arriving along the current ray makes it back to the camera.
+        let mut throughput = Vec3::from_one(1.0);
+        let mut ray = Ray {
+            origin: ray.origin,
+            direction: ray.direction,
+            time: ray.time,
+        };
+
+        for bounce in 0..self.max_depth {
+            let (point, material) = match world.hit(&ray, sampler) {
+                HitOrMiss::Hit {
+                    point, material, ..
+                } => (point, material),
+                HitOrMiss::Miss => {
+                    color = color + throughput * world.environment.color(&ray);
+                    break;
+                }
+            };
+            color = color + throughput * material.emitted(&point);
+
+            let sample = match material.sample(&ray, &point, sampler) {
+                Some(sample) => sample,
+                None => break,
+            };
+            throughput = throughput * sample.weight;
+            if bounce + 1 >= self.roulette_depth {
+                throughput = match russian_roulette(throughput, sampler) {
+                    Some(throughput) => throughput,
+                    None => break,
+                };
+            }
+            ray = Ray {
+                origin: point.p,
+                direction: sample.direction,
+                time: ray.time,
+            };
+        }
+        color
+    }
+}
@@ -0,0 +1,123 @@
+//! A ray tracer following Peter Shirley's Ray Tracing In One Weekend.
+//!
+//! The renderer can be embedded in other programs: build a world out of Hittables such as
+//! Spheres, each made of a Material, point a Camera at it and render it into an Image.
+//!
+//! ```
+//! use rays::environment::Environment;
+//! use rays::camera::CameraSettings;
+//! use rays::integrator::PathTracer;
+//! use rays::material::Lambertian;
+//! use rays::output::Output;
+//! use rays::render::{render, Settings};
+//! use rays::{HittableList, Sphere, Vec3};
+//!
+//! let world = HittableList {
+//!     hittables: vec![Box::from(Sphere {
+//!         center: Vec3::zero(),
+//!         r: 1.0,
+//!         material: Box::from(Lambertian::make(Vec3::from_one(0.5))),
+//!     })],
+//! };
+//! let camera = CameraSettings {
+//!     lookfrom: Vec3 { x: 0.0, y: 0.0, z: 5.0 },
+//!     lookat: Vec3::zero(),
+//!     vup: Vec3 { x: 0.0, y: 1.0, z: 0.0 },
+//!     vfov: 40.0,
+//!     aperture: 0.0,
+//!     focus_dist: 5.0,
+//!     shutter_open: 0.0,
+//!     shutter_close: 0.0,
+//! }
+//! .camera(2.0);
+//! let settings = Settings {
+//!     width: 40,
+//!     height: 20,
+//!     samples_per_pixel: 4,
+//!     integrator: Box::from(PathTracer::make(10, 3)),
+//!     threads: 2,
+//!     environment: Environment::sky(),
+//!     seed: 0,
+//!     progressive: false,
+//!     aovs: vec![],
+//! };
+//!
+//! // Outputs are told about the render as it happens. This one ignores it all.
+//! struct Quiet;
+//! impl Output for Quiet {
+//!     fn finish(&mut self, _frame: &rays::render::Frame) -> std::io::Result<()> {
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let frame = render(&camera, &world, &settings, &mut Quiet);
+//! assert_eq!((frame.image.width, frame.image.height), (40, 20));
+//! ```
+//!
+//! Scenes can also be loaded from TOML files with [scene::load].
+#![warn(missing_docs)]
+
+/// Axis-aligned bounding boxes.
+pub mod aabb;
+pub mod aov;
+/// Bounding volume hierarchies, for quickly finding what a ray hits among many objects.
+pub mod bvh;
+/// Cameras, which generate the rays for each pixel.
+pub mod camera;
+pub mod cli;
+/// Axis-aligned boxes.
+pub mod cuboid;
+/// Flat circles.
+pub mod disk;
+/// The light from rays that escape the world without hitting anything.
+pub mod environment;
+/// Rays hitting objects.
+pub mod hit;
+/// Images in memory, and loading them from files.
+pub mod image;
+/// Objects placed in the world with transforms, sharing their geometry.
+pub mod instance;
+/// Integrators, which work out the light arriving along rays by following them around the
+/// world.
+pub mod integrator;
+/// Lights, for sampling the light falling directly on surfaces.
+pub mod light;
+/// How surfaces scatter and give off light.
+pub mod material;
+/// Fog, smoke and other participating media, which scatter light inside a volume.
+pub mod medium;
+/// Spheres moving while the shutter is open.
+pub mod moving_sphere;
+pub mod obj;
+/// Where renders are sent: files and windows.
+pub mod output;
+/// Infinite planes.
+pub mod plane;
+/// Parallelograms and rectangles.
+pub mod quad;
+/// Rays.
+pub mod ray;
+/// Rendering a world into an image, in parallel.
+pub mod render;
+/// Repeatable random numbers.
+pub mod sampler;
+pub mod scene;
+/// Spheres.
+pub mod sphere;
+/// Colors varying over surfaces.
+pub mod texture;
+/// Moving, turning and stretching objects.
+pub mod transform;
+/// Triangle meshes.
+pub mod triangle;
+/// 3D vectors.
+pub mod vec3;
+/// Dense 3D grids of values, such as the density of smoke, loaded from files.
+pub mod voxel;
+
+pub use crate::camera::Camera;
+pub use crate::hit::{Hittable, HittableList};
+pub use crate::material::Material;
+pub use crate::ray::Ray;
+pub use crate::sphere::Sphere;
+pub use crate::vec3::Vec3;
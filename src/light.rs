@@ -0,0 +1,278 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use crate::sampler::Sampler;
+use crate::vec3::Vec3;
+
+/// The shape of something giving off light, for sampling directions towards it. Picking
+/// directions that reach a light lets the renderer find the light falling directly on each
+/// surface, rather than waiting for randomly bounced rays to stumble across small lights.
+///
+/// Lights only describe where light comes from. How bright it is, and whether anything is in the
+/// way, is found by tracing a shadow ray in the chosen direction.
+///
+/// Lights are shared between render threads, so must be Send and Sync.
+pub trait Light: Send + Sync {
+    /// Pick a direction from origin towards the light, or None if the light can't be sampled
+    /// from there.
+    fn sample(&self, origin: Vec3, sampler: &mut Sampler) -> Option<Vec3>;
+
+    /// The probability density, per unit solid angle, of sample picking the given direction
+    /// from origin. Zero if the direction misses the light.
+    fn pdf(&self, origin: Vec3, direction: Vec3) -> f64;
+}
+
+/// Shared Lights, such as an environment map also used to color rays that escape the world.
+impl<L: Light + ?Sized> Light for Arc<L> {
+    fn sample(&self, origin: Vec3, sampler: &mut Sampler) -> Option<Vec3> {
+        (**self).sample(origin, sampler)
+    }
+
+    fn pdf(&self, origin: Vec3, direction: Vec3) -> f64 {
+        (**self).pdf(origin, direction)
+    }
+}
+
+/// A List of Lights that is itself a Light, picking one of them at random for each sample.
+pub struct LightList {
+    /// The lights in the list.
+    pub lights: Vec<Box<dyn Light>>,
+}
+
+impl Light for LightList {
+    fn sample(&self, origin: Vec3, sampler: &mut Sampler) -> Option<Vec3> {
+        if self.lights.is_empty() {
+            return None;
+        }
+        let index = ((sampler.random_double() * self.lights.len() as f64) as usize)
+            .min(self.lights.len() - 1);
+        self.lights[index].sample(origin, sampler)
+    }
+
+    /// The average of the densities of every light, since each is picked equally often. Lights
+    /// overlapping the direction all count, whichever of them was picked.
+    fn pdf(&self, origin: Vec3, direction: Vec3) -> f64 {
+        if self.lights.is_empty() {
+            return 0.0;
+        }
+        let total: f64 = self
+            .lights
+            .iter()
+            .map(|light| light.pdf(origin, direction))
+            .sum();
+        total / self.lights.len() as f64
+    }
+}
+
+/// A spherical light. Directions are picked evenly from the cone of directions from the origin
+/// that hit the sphere, so every direction sampled reaches it.
+#[derive(Copy, Clone, Debug)]
+pub struct SphereLight {
+    /// The center of the sphere.
+    pub center: Vec3,
+    /// The radius of the sphere.
+    pub r: f64,
+}
+
+impl SphereLight {
+    /// The cosine of the angle between the center of the sphere and its edge, seen from origin,
+    /// or None if origin is inside the sphere.
+    fn cos_theta_max(&self, origin: Vec3) -> Option<f64> {
+        let distance_squared = (self.center - origin).norm();
+        let r_squared = self.r * self.r;
+        if distance_squared <= r_squared {
+            return None;
+        }
+        Some((1.0 - r_squared / distance_squared).sqrt())
+    }
+}
+
+impl Light for SphereLight {
+    fn sample(&self, origin: Vec3, sampler: &mut Sampler) -> Option<Vec3> {
+        let cos_theta_max = self.cos_theta_max(origin)?;
+        let cos_theta = 1.0 - sampler.random_double() * (1.0 - cos_theta_max);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * sampler.random_double();
+
+        // Build axes around the direction to the center of the sphere.
+        let w = (self.center - origin).normalize();
+        let a = if w.x.abs() > 0.9 {
+            Vec3 {
+                x: 0.0,
+                y: 1.0,
+                z: 0.0,
+            }
+        } else {
+            Vec3 {
+                x: 1.0,
+                y: 0.0,
+                z: 0.0,
+            }
+        };
+        let v = w.cross(&a).normalize();
+        let u = w.cross(&v);
+        Some(sin_theta * phi.cos() * u + sin_theta * phi.sin() * v + cos_theta * w)
+    }
+
+    fn pdf(&self, origin: Vec3, direction: Vec3) -> f64 {
+        let cos_theta_max = match self.cos_theta_max(origin) {
+            Some(cos_theta_max) => cos_theta_max,
+            None => return 0.0,
+        };
+        let to_center = self.center - origin;
+        let cos_theta = direction.normalize().dot(&to_center.normalize());
+        if cos_theta < cos_theta_max {
+            return 0.0;
+        }
+        1.0 / (2.0 * PI * (1.0 - cos_theta_max))
+    }
+}
+
+/// A light shaped like a Quad. Points are picked evenly over its area, so larger parts of it seen
+/// from the origin are picked more often. Since lights only shine from their front face, the
+/// quad is only sampled from in front of it.
+#[derive(Copy, Clone, Debug)]
+pub struct QuadLight {
+    /// One corner of the quad.
+    pub corner: Vec3,
+    /// One side of the quad, from the corner.
+    pub u: Vec3,
+    /// The other side of the quad, from the corner.
+    pub v: Vec3,
+}
+
+impl QuadLight {
+    /// The normal of the quad's front face, whose length is its area.
+    fn area_normal(&self) -> Vec3 {
+        self.u.cross(&self.v)
+    }
+}
+
+impl Light for QuadLight {
+    fn sample(&self, origin: Vec3, sampler: &mut Sampler) -> Option<Vec3> {
+        if self.area_normal().dot(&(origin - self.corner)) <= 0.0 {
+            return None;
+        }
+        let point =
+            self.corner + sampler.random_double() * self.u + sampler.random_double() * self.v;
+        Some(point - origin)
+    }
+
+    /// The density of picking a point, one over the area, turned into a density per unit solid
+    /// angle by dividing by how much solid angle a small patch at the point covers from origin.
+    fn pdf(&self, origin: Vec3, direction: Vec3) -> f64 {
+        let n = self.area_normal();
+        let area = n.magnitude();
+        let denominator = n.dot(&direction);
+        // The direction has to reach the front of the quad.
+        if area == 0.0 || denominator >= 0.0 {
+            return 0.0;
+        }
+        let t = n.dot(&(self.corner - origin)) / denominator;
+        if t <= 0.0 {
+            return 0.0;
+        }
+        let offset = origin + t * direction - self.corner;
+        let w = n / n.norm();
+        let alpha = w.dot(&offset.cross(&self.v));
+        let beta = w.dot(&self.u.cross(&offset));
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return 0.0;
+        }
+        let distance_squared = (t * direction).norm();
+        let cosine = -denominator / (area * direction.magnitude());
+        distance_squared / (cosine * area)
+    }
+}
+
+#[test]
+fn test_quad_light_samples_consistently_with_its_pdf() {
+    // A 2 by 1 light in the ceiling, facing down.
+    let light = QuadLight {
+        corner: Vec3 {
+            x: -1.0,
+            y: 3.0,
+            z: -1.0,
+        },
+        u: Vec3 {
+            x: 2.0,
+            y: 0.0,
+            z: 0.0,
+        },
+        v: Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: 1.0,
+        },
+    };
+    let origin = Vec3 {
+        x: 0.5,
+        y: 0.0,
+        z: 0.0,
+    };
+    let mut sampler = Sampler::make(0);
+    for _ in 0..1000 {
+        let direction = light.sample(origin, &mut sampler).unwrap();
+        assert!(light.pdf(origin, direction) > 0.0);
+    }
+    // The density integrates to one over every direction.
+    let samples = 400000;
+    let total: f64 = (0..samples)
+        .map(|_| light.pdf(origin, Vec3::random_unit_vector(&mut sampler)))
+        .sum();
+    assert!((total * 4.0 * PI / samples as f64 - 1.0).abs() < 0.03);
+    // Nothing is sampled from behind the light.
+    let above = Vec3 {
+        x: 0.0,
+        y: 5.0,
+        z: 0.0,
+    };
+    assert!(light.sample(above, &mut sampler).is_none());
+    assert_eq!(0.0, light.pdf(above, origin - above));
+}
+
+#[test]
+fn test_sphere_light_samples_only_directions_that_hit_it() {
+    use crate::hit::{HitOrMiss, Hittable};
+    use crate::material::diffuse_light::DiffuseLight;
+    use crate::ray::Ray;
+    use crate::sphere::Sphere;
+
+    let light = SphereLight {
+        center: Vec3 {
+            x: 1.0,
+            y: 3.0,
+            z: -2.0,
+        },
+        r: 0.5,
+    };
+    let sphere = Sphere {
+        center: light.center,
+        r: light.r,
+        material: Box::from(DiffuseLight::make(Vec3::from_one(1.0))),
+    };
+    let origin = Vec3::zero();
+    let mut sampler = Sampler::make(0);
+    for _ in 0..1000 {
+        let direction = light.sample(origin, &mut sampler).unwrap();
+        let ray = Ray {
+            origin,
+            direction,
+            time: 0.0,
+        };
+        assert!(matches!(
+            sphere.hit(&ray, 0.001, f64::INFINITY),
+            HitOrMiss::Hit { .. }
+        ));
+    }
+    // The fraction of all directions with a density is the fraction of the sky the sphere
+    // covers, and the density is one over the solid angle it covers.
+    let samples = 100000;
+    let hits = (0..samples)
+        .map(|_| light.pdf(origin, Vec3::random_unit_vector(&mut sampler)))
+        .filter(|&pdf| pdf > 0.0)
+        .count();
+    let solid_angle = 1.0 / light.pdf(origin, light.center);
+    assert!((hits as f64 / samples as f64 - solid_angle / (4.0 * PI)).abs() < 0.002);
+    assert_eq!(0.0, light.pdf(origin, -light.center));
+}
@@ -1,31 +1,286 @@
+use std::sync::Arc;
+
+use crate::hit::SurfacePoint;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::vec3::Vec3;
 
+/// Physically based metals.
+pub mod conductor;
+/// Glass and other clear materials.
 pub mod dielectric;
+/// Materials which give off light.
+pub mod diffuse_light;
+/// Media scattering light mostly forwards or backwards.
+pub mod henyey_greenstein;
+/// Media scattering light equally in every direction.
+pub mod isotropic;
+/// Ideal matte materials.
 pub mod lambertian;
+/// Mirrors and brushed metals.
 pub mod metal;
+/// The GGX microfacet model and Fresnel equations shared by the physically based materials.
+pub mod microfacet;
+/// Matte materials scattering equally in every direction.
 pub mod uniform_scatterer;
 
-/// The result of scattering a ray from a Material.
-pub enum ScatterResult {
-    Scattered {
-        scatter_direction: Vec3,
-        attenuation: Vec3,
-    },
-    Absorbed {},
+pub use conductor::{Conductor, MetalKind};
+pub use dielectric::Dielectric;
+pub use diffuse_light::DiffuseLight;
+pub use henyey_greenstein::HenyeyGreenstein;
+pub use isotropic::Isotropic;
+pub use lambertian::Lambertian;
+pub use metal::Metal;
+pub use uniform_scatterer::UniformScatterer;
+
+/// A direction picked by a Material for the light arriving along a ray to have come from, and
+/// how much of that light it scatters back along the ray.
+#[derive(Copy, Clone, Debug)]
+pub struct BsdfSample {
+    /// The direction to trace next, away from the surface.
+    pub direction: Vec3,
+    /// How much of each color of the light arriving from direction is scattered back along the
+    /// ray, divided by the pdf: eval / pdf for smooth lobes.
+    pub weight: Vec3,
+    /// The probability density, per unit solid angle, of picking direction. For delta lobes,
+    /// which only scatter in exact directions, the probability of picking that lobe instead.
+    pub pdf: f64,
+    /// Whether the direction came from a delta lobe, like a mirror reflection or a refraction
+    /// through glass. Delta lobes can't be evaluated for other directions, so eval and pdf leave
+    /// them out, and lights can't be sampled through them.
+    pub delta: bool,
 }
 
-pub trait Material {
-    /// Scatter a ray of a given attenuation that hit this Material according to the hit_record.
-    /// Returns a ScatterResult.
-    fn scatter(&self, ray_in: &Ray, normal: Vec3, front_face: bool) -> ScatterResult;
+/// What a surface is made of: how it scatters light hitting it, and any light it gives off.
+///
+/// How a Material scatters light is described by its BSDF, which gives the fraction of light
+/// arriving from one direction that is scattered along another. Materials can pick directions to
+/// follow with sample, and be evaluated for any direction with eval and pdf, so that other ways
+/// of picking directions, like sampling the lights, can be combined with the Material's own.
+///
+/// Materials are shared between render threads, so must be Send and Sync.
+pub trait Material: Send + Sync {
+    /// Pick a direction for the light scattered back along ray_in at the given point to have come
+    /// from, drawing any random numbers needed from the sampler. Returns None if the ray is
+    /// absorbed.
+    fn sample(&self, ray_in: &Ray, point: &SurfacePoint, sampler: &mut Sampler)
+        -> Option<BsdfSample>;
+
+    /// The BSDF for light arriving from direction and scattered back along ray_in, times the
+    /// cosine of the angle between direction and the normal. Only smooth lobes are included,
+    /// so materials with only delta lobes, like mirrors and glass, are zero everywhere.
+    fn eval(&self, _ray_in: &Ray, _point: &SurfacePoint, _direction: Vec3) -> Vec3 {
+        Vec3::zero()
+    }
+
+    /// The probability density, per unit solid angle, of sample picking direction from the
+    /// smooth lobes. Zero for materials with only delta lobes.
+    fn pdf(&self, _ray_in: &Ray, _point: &SurfacePoint, _direction: Vec3) -> f64 {
+        0.0
+    }
+
+    /// The light given off by the Material where it was hit. Most materials give off none.
+    fn emitted(&self, _point: &SurfacePoint) -> Vec3 {
+        Vec3::zero()
+    }
+
+    /// Whether the Material gives off any light, so that objects made of it should be sampled as
+    /// lights.
+    fn emits_light(&self) -> bool {
+        false
+    }
+
+    /// The color of the Material where it was hit, ignoring lighting. Used for the albedo AOV.
+    /// Materials which only give off light have none.
+    fn albedo(&self, _point: &SurfacePoint) -> Vec3 {
+        Vec3::zero()
+    }
 }
 
-fn reflect(ray_in: &Ray, normal: Vec3, fuzz: f64) -> Vec3 {
+/// A Material shared between several objects, such as the sides of a Cuboid.
+impl<M: Material + ?Sized> Material for Arc<M> {
+    fn sample(
+        &self,
+        ray_in: &Ray,
+        point: &SurfacePoint,
+        sampler: &mut Sampler,
+    ) -> Option<BsdfSample> {
+        (**self).sample(ray_in, point, sampler)
+    }
+
+    fn eval(&self, ray_in: &Ray, point: &SurfacePoint, direction: Vec3) -> Vec3 {
+        (**self).eval(ray_in, point, direction)
+    }
+
+    fn pdf(&self, ray_in: &Ray, point: &SurfacePoint, direction: Vec3) -> f64 {
+        (**self).pdf(ray_in, point, direction)
+    }
+
+    fn emitted(&self, point: &SurfacePoint) -> Vec3 {
+        (**self).emitted(point)
+    }
+
+    fn emits_light(&self) -> bool {
+        (**self).emits_light()
+    }
+
+    fn albedo(&self, point: &SurfacePoint) -> Vec3 {
+        (**self).albedo(point)
+    }
+}
+
+fn reflect(ray_in: &Ray, normal: Vec3, fuzz: f64, sampler: &mut Sampler) -> Vec3 {
     let v = ray_in.direction.normalize();
     // Vector calculation for getting the reflected ray direction.
     // Plus the random fuzz of the material
-    let scatter_direction =
-        v - 2.0 * v.dot(&normal) * normal + (fuzz * Vec3::random_in_unit_sphere());
-    scatter_direction
+    v - 2.0 * v.dot(&normal) * normal + (fuzz * Vec3::random_in_unit_sphere(sampler))
+}
+
+#[test]
+fn test_materials_sample_consistently_with_eval_and_pdf() {
+    let point = SurfacePoint {
+        p: Vec3::zero(),
+        normal: Vec3 {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        },
+        front_face: true,
+        u: 0.0,
+        v: 0.0,
+    };
+    let ray_in = Ray {
+        origin: Vec3 {
+            x: -1.0,
+            y: 1.0,
+            z: 0.0,
+        },
+        direction: Vec3 {
+            x: 1.0,
+            y: -1.0,
+            z: 0.0,
+        },
+        time: 0.0,
+    };
+    let mut sampler = Sampler::make(0);
+    let color = Vec3 {
+        x: 0.2,
+        y: 0.4,
+        z: 0.8,
+    };
+
+    let smooth: [Box<dyn Material>; 5] = [
+        Box::from(Lambertian::make(color)),
+        Box::from(UniformScatterer::make(color)),
+        Box::from(Isotropic::make(color)),
+        Box::from(HenyeyGreenstein::make(color, 0.3)),
+        Box::from(HenyeyGreenstein::make(color, -0.3)),
+    ];
+    for material in &smooth {
+        for _ in 0..100 {
+            let sample = material.sample(&ray_in, &point, &mut sampler).unwrap();
+            assert!(!sample.delta);
+            assert!((sample.pdf - material.pdf(&ray_in, &point, sample.direction)).abs() < 1e-12);
+            let weight = material.eval(&ray_in, &point, sample.direction) / sample.pdf;
+            assert!((weight - sample.weight).magnitude() < 1e-9);
+        }
+        // The density over every direction adds up to one, estimated with directions picked
+        // evenly over the sphere.
+        let samples = 100000;
+        let total: f64 = (0..samples)
+            .map(|_| material.pdf(&ray_in, &point, Vec3::random_unit_vector(&mut sampler)))
+            .sum();
+        assert!((total * 4.0 * std::f64::consts::PI / samples as f64 - 1.0).abs() < 0.01);
+    }
+
+    let delta: [Box<dyn Material>; 2] = [
+        Box::from(Metal::make(color, 0.0)),
+        Box::from(Dielectric::make(1.5)),
+    ];
+    for material in &delta {
+        let sample = material.sample(&ray_in, &point, &mut sampler).unwrap();
+        assert!(sample.delta);
+        assert_eq!(Vec3::zero(), material.eval(&ray_in, &point, sample.direction));
+        assert_eq!(0.0, material.pdf(&ray_in, &point, sample.direction));
+    }
+}
+
+#[test]
+fn test_rough_materials_sample_consistently_with_eval_and_pdf() {
+    let ray_in = Ray {
+        origin: Vec3 {
+            x: -1.0,
+            y: 2.0,
+            z: 0.5,
+        },
+        direction: Vec3 {
+            x: 1.0,
+            y: -2.0,
+            z: -0.5,
+        },
+        time: 0.0,
+    };
+    let mut sampler = Sampler::make(0);
+    let rough: [Box<dyn Material>; 3] = [
+        Box::from(Conductor::metal(MetalKind::Gold, 0.5)),
+        Box::from(Dielectric::rough(1.5, 0.5)),
+        Box::from(Dielectric::rough(1.5, 0.8)),
+    ];
+    // Rays arriving at glass from outside and from inside.
+    for front_face in [true, false] {
+        let point = SurfacePoint {
+            p: Vec3::zero(),
+            normal: Vec3 {
+                x: 0.0,
+                y: 1.0,
+                z: 0.0,
+            },
+            front_face,
+            u: 0.0,
+            v: 0.0,
+        };
+        for material in &rough {
+            let samples = 10000;
+            let mut scattered = 0;
+            for _ in 0..samples {
+                let sample = match material.sample(&ray_in, &point, &mut sampler) {
+                    Some(sample) => sample,
+                    None => continue,
+                };
+                scattered += 1;
+                assert!(!sample.delta);
+                let pdf = material.pdf(&ray_in, &point, sample.direction);
+                assert!((sample.pdf - pdf).abs() < 1e-6 * pdf.max(1.0));
+                let weight = material.eval(&ray_in, &point, sample.direction) / sample.pdf;
+                assert!((weight - sample.weight).magnitude() < 1e-6);
+            }
+            // Samples blocked by other microfacets are lost, so the density over every direction
+            // adds up to the fraction of samples which scatter, estimated with directions picked
+            // evenly over the sphere.
+            let directions = 1000000;
+            let total: f64 = (0..directions)
+                .map(|_| material.pdf(&ray_in, &point, Vec3::random_unit_vector(&mut sampler)))
+                .sum();
+            let scattered = scattered as f64 / samples as f64;
+            assert!(scattered > 0.7);
+            let total = total * 4.0 * std::f64::consts::PI / directions as f64;
+            assert!((total - scattered).abs() < 0.02);
+        }
+    }
+
+    // Without roughness, both are delta lobes.
+    let point = SurfacePoint {
+        p: Vec3::zero(),
+        normal: Vec3 {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        },
+        front_face: true,
+        u: 0.0,
+        v: 0.0,
+    };
+    let conductor = Conductor::metal(MetalKind::Silver, 0.0);
+    assert!(conductor.sample(&ray_in, &point, &mut sampler).unwrap().delta);
+    assert_eq!(0.0, conductor.pdf(&ray_in, &point, -ray_in.direction));
 }
@@ -0,0 +1,150 @@
+use serde::Deserialize;
+
+use crate::hit::SurfacePoint;
+use crate::material::microfacet::{fresnel_conductor, reflect, Frame, Ggx};
+use crate::material::{BsdfSample, Material};
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::vec3::Vec3;
+
+/// Metals with measured indices of refraction, for use with Conductor::metal.
+#[derive(Copy, Clone, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetalKind {
+    /// Yellow, reflecting little blue light.
+    Gold,
+    /// The most reflective metal, almost white.
+    Silver,
+    /// Orange, reflecting little green or blue light.
+    Copper,
+    /// Bright and slightly blue.
+    Aluminium,
+}
+
+impl MetalKind {
+    /// The real (eta) and imaginary (k) parts of the metal's index of refraction, for red, green
+    /// and blue light of about 650, 550 and 450nm.
+    pub fn eta_k(&self) -> (Vec3, Vec3) {
+        let (eta, k) = match self {
+            MetalKind::Gold => ([0.143, 0.374, 1.442], [3.983, 2.385, 1.603]),
+            MetalKind::Silver => ([0.155, 0.117, 0.138], [4.828, 3.122, 2.147]),
+            MetalKind::Copper => ([0.200, 0.924, 1.102], [3.912, 2.452, 2.142]),
+            MetalKind::Aluminium => ([1.657, 0.880, 0.521], [9.224, 6.270, 4.837]),
+        };
+        let vec3 = |c: [f64; 3]| Vec3 {
+            x: c[0],
+            y: c[1],
+            z: c[2],
+        };
+        (vec3(eta), vec3(k))
+    }
+}
+
+/// A physically based metal. Its surface is made of tiny mirrors facing in directions given by
+/// the GGX microfacet distribution, which reflect light as the Fresnel equations for the metal's
+/// complex index of refraction say, so its color changes towards white at grazing angles as
+/// real metals do. Light blocked by other microfacets on its way in or out is lost, following
+/// the Smith model.
+pub struct Conductor {
+    eta: Vec3,
+    k: Vec3,
+    ggx: Ggx,
+}
+
+impl Conductor {
+    /// Create a metal with the given complex index of refraction, eta + ik, for red, green and
+    /// blue light. Roughness runs from 0 for a perfect mirror to 1 for a very rough surface.
+    pub fn make(eta: Vec3, k: Vec3, roughness: f64) -> Conductor {
+        Conductor {
+            eta,
+            k,
+            ggx: Ggx::from_roughness(roughness),
+        }
+    }
+
+    /// Create one of the metals with a measured index of refraction.
+    pub fn metal(kind: MetalKind, roughness: f64) -> Conductor {
+        let (eta, k) = kind.eta_k();
+        Conductor::make(eta, k, roughness)
+    }
+}
+
+impl Material for Conductor {
+    /// A delta lobe if the metal is smooth. Otherwise a microfacet normal is picked from those
+    /// visible from the incoming ray, and the ray reflected about it.
+    fn sample(
+        &self,
+        ray_in: &Ray,
+        point: &SurfacePoint,
+        sampler: &mut Sampler,
+    ) -> Option<BsdfSample> {
+        let frame = Frame::make(point.normal);
+        let wo = frame.to_local(-ray_in.direction.normalize());
+        if wo.z <= 0.0 {
+            return None;
+        }
+        if self.ggx.is_smooth() {
+            let wi = Vec3 {
+                x: -wo.x,
+                y: -wo.y,
+                z: wo.z,
+            };
+            return Some(BsdfSample {
+                direction: frame.to_world(wi),
+                weight: fresnel_conductor(wo.z, self.eta, self.k),
+                pdf: 1.0,
+                delta: true,
+            });
+        }
+
+        let h = self
+            .ggx
+            .sample_visible(wo, sampler.random_double(), sampler.random_double());
+        let wi = reflect(wo, h);
+        // Reflected into the surface, so blocked by the other microfacets.
+        if wi.z <= 0.0 {
+            return None;
+        }
+        let fresnel = fresnel_conductor(wo.dot(&h), self.eta, self.k);
+        Some(BsdfSample {
+            direction: frame.to_world(wi),
+            weight: self.ggx.g(wo, wi) / self.ggx.g1(wo) * fresnel,
+            pdf: self.ggx.visible_d(wo, h) / (4.0 * wo.dot(&h)),
+            delta: false,
+        })
+    }
+
+    fn eval(&self, ray_in: &Ray, point: &SurfacePoint, direction: Vec3) -> Vec3 {
+        if self.ggx.is_smooth() {
+            return Vec3::zero();
+        }
+        let frame = Frame::make(point.normal);
+        let wo = frame.to_local(-ray_in.direction.normalize());
+        let wi = frame.to_local(direction.normalize());
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return Vec3::zero();
+        }
+        let h = (wo + wi).normalize();
+        let fresnel = fresnel_conductor(wo.dot(&h), self.eta, self.k);
+        self.ggx.d(h) * self.ggx.g(wo, wi) / (4.0 * wo.z) * fresnel
+    }
+
+    fn pdf(&self, ray_in: &Ray, point: &SurfacePoint, direction: Vec3) -> f64 {
+        if self.ggx.is_smooth() {
+            return 0.0;
+        }
+        let frame = Frame::make(point.normal);
+        let wo = frame.to_local(-ray_in.direction.normalize());
+        let wi = frame.to_local(direction.normalize());
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return 0.0;
+        }
+        let h = (wo + wi).normalize();
+        self.ggx.visible_d(wo, h) / (4.0 * wo.dot(&h))
+    }
+
+    /// The color of the metal seen head on.
+    fn albedo(&self, _point: &SurfacePoint) -> Vec3 {
+        fresnel_conductor(1.0, self.eta, self.k)
+    }
+}
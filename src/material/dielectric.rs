@@ -1,17 +1,105 @@
-use crate::material::{reflect, Material, ScatterResult};
-use crate::random_double;
+use crate::hit::SurfacePoint;
+use crate::material::microfacet::{fresnel_dielectric, Frame, Ggx};
+use crate::material::{microfacet, reflect, BsdfSample, Material};
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::vec3::Vec3;
 
+/// A clear Material like glass or water, which reflects some light and refracts the rest, as
+/// given by the Fresnel equations.
+///
+/// Rough dielectrics, like frosted glass, are made of tiny smooth facets facing in directions
+/// given by the GGX microfacet distribution, following Walter et al., "Microfacet Models for
+/// Refraction through Rough Surfaces". Like smooth ones, they leave out the change in radiance
+/// as light is squeezed into a denser material, which cancels out for light passing through.
 pub struct Dielectric {
     index_of_refraction: f64,
+    ggx: Ggx,
 }
 
 impl Dielectric {
+    /// Create a smooth dielectric with the given index of refraction, such as 1.5 for glass.
     pub fn make(index_of_refraction: f64) -> Dielectric {
+        Dielectric::rough(index_of_refraction, 0.0)
+    }
+
+    /// Create a dielectric with a roughness from 0 for polished glass to 1 for very frosted
+    /// glass.
+    pub fn rough(index_of_refraction: f64, roughness: f64) -> Dielectric {
         Dielectric {
             index_of_refraction,
+            ggx: Ggx::from_roughness(roughness),
+        }
+    }
+
+    /// The index of refraction of the far side of the surface over that of the side the ray
+    /// arrives from.
+    fn eta(&self, point: &SurfacePoint) -> f64 {
+        if point.front_face {
+            self.index_of_refraction
+        } else {
+            1.0 / self.index_of_refraction
+        }
+    }
+
+    /// The half vector between the local directions wo and wi, which must be on opposite sides
+    /// for refraction, facing wo. None if no microfacet could refract one into the other.
+    fn refraction_half_vector(wo: Vec3, wi: Vec3, eta: f64) -> Option<Vec3> {
+        let h = (wo + eta * wi).normalize();
+        let h = if h.z < 0.0 { -h } else { h };
+        if wo.dot(&h) <= 0.0 || wi.dot(&h) >= 0.0 {
+            None
+        } else {
+            Some(h)
+        }
+    }
+
+    /// The change in solid angle from the half vector to wi when refracting, squared in the
+    /// denominator of the BSDF and pdf.
+    fn refraction_denominator(wo: Vec3, wi: Vec3, h: Vec3, eta: f64) -> f64 {
+        let d = wi.dot(&h) + wo.dot(&h) / eta;
+        d * d
+    }
+
+    /// Pick a visible microfacet, then reflect or refract through it with the probability of
+    /// the light taking each. The weight is the same either way, as the Fresnel terms cancel.
+    fn sample_rough(
+        &self,
+        ray_in: &Ray,
+        point: &SurfacePoint,
+        sampler: &mut Sampler,
+    ) -> Option<BsdfSample> {
+        let frame = Frame::make(point.normal);
+        let wo = frame.to_local(-ray_in.direction.normalize());
+        if wo.z <= 0.0 {
+            return None;
         }
+        let eta = self.eta(point);
+        let h = self
+            .ggx
+            .sample_visible(wo, sampler.random_double(), sampler.random_double());
+        let reflectance = fresnel_dielectric(wo.dot(&h), eta);
+        let visible_d = self.ggx.visible_d(wo, h);
+        let (wi, pdf) = if reflectance >= 1.0 || reflectance > sampler.random_double() {
+            let wi = microfacet::reflect(wo, h);
+            if wi.z <= 0.0 {
+                return None;
+            }
+            (wi, reflectance * visible_d / (4.0 * wo.dot(&h)))
+        } else {
+            let wi = microfacet::refract(wo, h, eta)?;
+            if wi.z >= 0.0 {
+                return None;
+            }
+            let denominator = Dielectric::refraction_denominator(wo, wi, h, eta);
+            (wi, (1.0 - reflectance) * visible_d * wi.dot(&h).abs() / denominator)
+        };
+        Some(BsdfSample {
+            direction: frame.to_world(wi),
+            weight: Vec3::from_one(self.ggx.g(wo, wi) / self.ggx.g1(wo)),
+            pdf,
+            delta: false,
+        })
     }
 
     fn refract(incident_direction: Vec3, normal: Vec3, etai_over_etat: f64) -> Vec3 {
@@ -23,36 +111,101 @@ impl Dielectric {
         let r_out_parallel = -(((1.0 - r_out_perp.norm()).abs()).sqrt()) * normal;
         r_out_perp + r_out_parallel
     }
-
-    fn reflectance(cos_theta: f64, refraction_ratio: f64) -> f64 {
-        // Use Schlick's approximation for reflectance.
-        let r0 = ((1.0 - refraction_ratio) / (1.0 + refraction_ratio)).powi(2);
-        r0 + (1.0 - r0) * ((1.0 - cos_theta).powi(5))
-    }
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, ray_in: &Ray, normal: Vec3, front_face: bool) -> ScatterResult {
-        let refraction_ratio = if front_face {
-            1.0 / self.index_of_refraction
-        } else {
-            self.index_of_refraction
-        };
+    /// For smooth dielectrics, two delta lobes, reflecting and refracting, picked with the
+    /// probability of the light taking each. As the choice follows the light, each lobe carries
+    /// back all of it.
+    fn sample(
+        &self,
+        ray_in: &Ray,
+        point: &SurfacePoint,
+        sampler: &mut Sampler,
+    ) -> Option<BsdfSample> {
+        if !self.ggx.is_smooth() {
+            return self.sample_rough(ray_in, point, sampler);
+        }
+        let normal = point.normal;
+        let eta = self.eta(point);
+        let refraction_ratio = 1.0 / eta;
 
         let incident_direction = ray_in.direction.normalize();
         let cos_theta = (-incident_direction).dot(&normal).min(1.0);
-        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
-        let total_internal_reflection = refraction_ratio * sin_theta > 1.0;
-        let scatter_direction = if total_internal_reflection
-            || Dielectric::reflectance(cos_theta, refraction_ratio) > random_double()
-        {
-            reflect(ray_in, normal, 0.0)
+        // Total internal reflection gives a reflectance of 1.
+        let reflectance = fresnel_dielectric(cos_theta, eta);
+        let (direction, pdf) = if reflectance >= 1.0 || reflectance > sampler.random_double() {
+            (reflect(ray_in, normal, 0.0, sampler), reflectance)
         } else {
-            Dielectric::refract(incident_direction, normal, refraction_ratio)
+            (
+                Dielectric::refract(incident_direction, normal, refraction_ratio),
+                1.0 - reflectance,
+            )
         };
-        ScatterResult::Scattered {
-            scatter_direction,
-            attenuation: Vec3::from_one(1.0),
+        Some(BsdfSample {
+            direction,
+            weight: Vec3::from_one(1.0),
+            pdf,
+            delta: true,
+        })
+    }
+
+    fn eval(&self, ray_in: &Ray, point: &SurfacePoint, direction: Vec3) -> Vec3 {
+        if self.ggx.is_smooth() {
+            return Vec3::zero();
         }
+        let frame = Frame::make(point.normal);
+        let wo = frame.to_local(-ray_in.direction.normalize());
+        let wi = frame.to_local(direction.normalize());
+        if wo.z <= 0.0 || wi.z == 0.0 {
+            return Vec3::zero();
+        }
+        let eta = self.eta(point);
+        let g = self.ggx.g(wo, wi);
+        let value = if wi.z > 0.0 {
+            let h = (wo + wi).normalize();
+            self.ggx.d(h) * g * fresnel_dielectric(wo.dot(&h), eta) / (4.0 * wo.z)
+        } else {
+            let h = match Dielectric::refraction_half_vector(wo, wi, eta) {
+                Some(h) => h,
+                None => return Vec3::zero(),
+            };
+            let transmittance = 1.0 - fresnel_dielectric(wo.dot(&h), eta);
+            let denominator = Dielectric::refraction_denominator(wo, wi, h, eta);
+            transmittance * self.ggx.d(h) * g * (wi.dot(&h) * wo.dot(&h)).abs()
+                / (wo.z * denominator)
+        };
+        Vec3::from_one(value)
+    }
+
+    fn pdf(&self, ray_in: &Ray, point: &SurfacePoint, direction: Vec3) -> f64 {
+        if self.ggx.is_smooth() {
+            return 0.0;
+        }
+        let frame = Frame::make(point.normal);
+        let wo = frame.to_local(-ray_in.direction.normalize());
+        let wi = frame.to_local(direction.normalize());
+        if wo.z <= 0.0 || wi.z == 0.0 {
+            return 0.0;
+        }
+        let eta = self.eta(point);
+        if wi.z > 0.0 {
+            let h = (wo + wi).normalize();
+            let reflectance = fresnel_dielectric(wo.dot(&h), eta);
+            reflectance * self.ggx.visible_d(wo, h) / (4.0 * wo.dot(&h))
+        } else {
+            let h = match Dielectric::refraction_half_vector(wo, wi, eta) {
+                Some(h) => h,
+                None => return 0.0,
+            };
+            let transmittance = 1.0 - fresnel_dielectric(wo.dot(&h), eta);
+            let denominator = Dielectric::refraction_denominator(wo, wi, h, eta);
+            transmittance * self.ggx.visible_d(wo, h) * wi.dot(&h).abs() / denominator
+        }
+    }
+
+    /// Clear materials have no color of their own, so are white.
+    fn albedo(&self, _point: &SurfacePoint) -> Vec3 {
+        Vec3::from_one(1.0)
     }
 }
@@ -0,0 +1,48 @@
+use crate::hit::SurfacePoint;
+use crate::material::{BsdfSample, Material};
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::texture::solid_color::SolidColor;
+use crate::texture::Texture;
+use crate::vec3::Vec3;
+
+/// A Material that gives off light, and reflects none.
+pub struct DiffuseLight {
+    emit: Box<dyn Texture>,
+}
+
+impl DiffuseLight {
+    /// Create a light giving off the same color everywhere.
+    pub fn make(emit: Vec3) -> DiffuseLight {
+        DiffuseLight::textured(Box::from(SolidColor::make(emit)))
+    }
+
+    /// Create a light whose color is given by a texture.
+    pub fn textured(emit: Box<dyn Texture>) -> DiffuseLight {
+        DiffuseLight { emit }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn sample(
+        &self,
+        _ray_in: &Ray,
+        _point: &SurfacePoint,
+        _sampler: &mut Sampler,
+    ) -> Option<BsdfSample> {
+        None
+    }
+
+    /// Light is only given off from the front face, so the inside of a light stays dark.
+    fn emitted(&self, point: &SurfacePoint) -> Vec3 {
+        if point.front_face {
+            self.emit.value(point.u, point.v, point.p)
+        } else {
+            Vec3::zero()
+        }
+    }
+
+    fn emits_light(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,132 @@
+use std::f64::consts::PI;
+
+use crate::hit::SurfacePoint;
+use crate::material::{BsdfSample, Material};
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::texture::solid_color::SolidColor;
+use crate::texture::Texture;
+use crate::vec3::Vec3;
+
+/// The Henyey-Greenstein phase function, for media which scatter light mostly forwards, like
+/// clouds and haze, or mostly backwards. The albedo is the fraction of light scattered rather
+/// than absorbed at each scattering.
+///
+/// g is the average cosine of the angle light is turned through: positive values scatter
+/// forwards, negative ones backwards, and zero in every direction equally, like Isotropic. Like
+/// Isotropic, the normal is ignored.
+pub struct HenyeyGreenstein {
+    albedo: Box<dyn Texture>,
+    g: f64,
+}
+
+impl HenyeyGreenstein {
+    /// Create a Henyey-Greenstein phase function of a single color. g is clamped to stay strictly
+    /// between -1 and 1.
+    pub fn make(albedo: Vec3, g: f64) -> HenyeyGreenstein {
+        HenyeyGreenstein::textured(Box::from(SolidColor::make(albedo)), g)
+    }
+
+    /// Create a Henyey-Greenstein phase function whose color is given by a texture.
+    pub fn textured(albedo: Box<dyn Texture>, g: f64) -> HenyeyGreenstein {
+        HenyeyGreenstein {
+            albedo,
+            g: g.clamp(-0.999, 0.999),
+        }
+    }
+
+    /// The density of turning light through an angle with the given cosine.
+    fn phase(&self, cos_theta: f64) -> f64 {
+        let g = self.g;
+        let denominator = 1.0 + g * g - 2.0 * g * cos_theta;
+        (1.0 - g * g) / (4.0 * PI * denominator * denominator.sqrt())
+    }
+
+    /// The cosine of the angle between the incoming ray and the direction light scattered along
+    /// it came from.
+    fn cos_theta(ray_in: &Ray, direction: Vec3) -> f64 {
+        ray_in.direction.normalize().dot(&direction.normalize())
+    }
+}
+
+impl Material for HenyeyGreenstein {
+    /// Picks the angle to turn through by inverting the phase function's distribution.
+    fn sample(
+        &self,
+        ray_in: &Ray,
+        point: &SurfacePoint,
+        sampler: &mut Sampler,
+    ) -> Option<BsdfSample> {
+        let g = self.g;
+        let xi = sampler.random_double();
+        let cos_theta = if g.abs() < 1e-3 {
+            1.0 - 2.0 * xi
+        } else {
+            let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * xi);
+            ((1.0 + g * g - s * s) / (2.0 * g)).clamp(-1.0, 1.0)
+        };
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * sampler.random_double();
+
+        let w = ray_in.direction.normalize();
+        let (u, v) = w.perpendiculars();
+        let direction = sin_theta * phi.cos() * u + sin_theta * phi.sin() * v + cos_theta * w;
+        Some(BsdfSample {
+            direction,
+            weight: self.albedo.value(point.u, point.v, point.p),
+            pdf: self.phase(cos_theta),
+            delta: false,
+        })
+    }
+
+    /// The phase function has no cosine, as light is scattered by particles rather than a
+    /// surface.
+    fn eval(&self, ray_in: &Ray, point: &SurfacePoint, direction: Vec3) -> Vec3 {
+        self.phase(HenyeyGreenstein::cos_theta(ray_in, direction))
+            * self.albedo.value(point.u, point.v, point.p)
+    }
+
+    fn pdf(&self, ray_in: &Ray, _point: &SurfacePoint, direction: Vec3) -> f64 {
+        self.phase(HenyeyGreenstein::cos_theta(ray_in, direction))
+    }
+
+    fn albedo(&self, point: &SurfacePoint) -> Vec3 {
+        self.albedo.value(point.u, point.v, point.p)
+    }
+}
+
+#[test]
+fn test_average_cosine_of_scattering_is_g() {
+    let point = SurfacePoint {
+        p: Vec3::zero(),
+        normal: Vec3 {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        },
+        front_face: true,
+        u: 0.0,
+        v: 0.0,
+    };
+    let ray_in = Ray {
+        origin: Vec3::zero(),
+        direction: Vec3 {
+            x: 1.0,
+            y: 2.0,
+            z: -2.0,
+        },
+        time: 0.0,
+    };
+    let mut sampler = Sampler::make(0);
+    for g in [-0.7, 0.0, 0.5, 0.9] {
+        let material = HenyeyGreenstein::make(Vec3::from_one(1.0), g);
+        let samples = 100000;
+        let total: f64 = (0..samples)
+            .map(|_| {
+                let sample = material.sample(&ray_in, &point, &mut sampler).unwrap();
+                HenyeyGreenstein::cos_theta(&ray_in, sample.direction)
+            })
+            .sum();
+        assert!((total / samples as f64 - g).abs() < 0.01);
+    }
+}
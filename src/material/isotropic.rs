@@ -0,0 +1,61 @@
+use std::f64::consts::PI;
+
+use crate::hit::SurfacePoint;
+use crate::material::{BsdfSample, Material};
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::texture::solid_color::SolidColor;
+use crate::texture::Texture;
+use crate::vec3::Vec3;
+
+/// The phase function of a medium which scatters light equally in every direction, like thin
+/// fog or smoke. The albedo is the fraction of light scattered rather than absorbed at each
+/// scattering.
+///
+/// Meant for the particles inside a ConstantMedium, where there is no surface, so the normal is
+/// ignored and every direction on the whole sphere is equally likely.
+pub struct Isotropic {
+    albedo: Box<dyn Texture>,
+}
+
+impl Isotropic {
+    /// Create an isotropic phase function of a single color.
+    pub fn make(albedo: Vec3) -> Isotropic {
+        Isotropic::textured(Box::from(SolidColor::make(albedo)))
+    }
+
+    /// Create an isotropic phase function whose color is given by a texture.
+    pub fn textured(albedo: Box<dyn Texture>) -> Isotropic {
+        Isotropic { albedo }
+    }
+}
+
+impl Material for Isotropic {
+    fn sample(
+        &self,
+        _ray_in: &Ray,
+        point: &SurfacePoint,
+        sampler: &mut Sampler,
+    ) -> Option<BsdfSample> {
+        Some(BsdfSample {
+            direction: Vec3::random_unit_vector(sampler),
+            weight: self.albedo.value(point.u, point.v, point.p),
+            pdf: 1.0 / (4.0 * PI),
+            delta: false,
+        })
+    }
+
+    /// The phase function has no cosine, as light is scattered by particles rather than a
+    /// surface.
+    fn eval(&self, _ray_in: &Ray, point: &SurfacePoint, _direction: Vec3) -> Vec3 {
+        self.albedo.value(point.u, point.v, point.p) / (4.0 * PI)
+    }
+
+    fn pdf(&self, _ray_in: &Ray, _point: &SurfacePoint, _direction: Vec3) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+
+    fn albedo(&self, point: &SurfacePoint) -> Vec3 {
+        self.albedo.value(point.u, point.v, point.p)
+    }
+}
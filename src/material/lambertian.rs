@@ -1,26 +1,62 @@
-use crate::material::{Material, ScatterResult};
+use std::f64::consts::PI;
+
+use crate::hit::SurfacePoint;
+use crate::material::{BsdfSample, Material};
 use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::texture::solid_color::SolidColor;
+use crate::texture::Texture;
 use crate::vec3::Vec3;
 
+/// A matte Material, which scatters light in all directions, favouring those close to the
+/// surface normal.
 pub struct Lambertian {
-    albedo: Vec3,
+    albedo: Box<dyn Texture>,
 }
 
 impl Lambertian {
+    /// Create a Lambertian material of a single color.
     pub fn make(albedo: Vec3) -> Lambertian {
+        Lambertian::textured(Box::from(SolidColor::make(albedo)))
+    }
+
+    /// Create a Lambertian material whose color is given by a texture.
+    pub fn textured(albedo: Box<dyn Texture>) -> Lambertian {
         Lambertian { albedo }
     }
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, _ray_in: &Ray, normal: Vec3, _front_face: bool) -> ScatterResult {
-        let mut scatter_direction = normal + Vec3::random_unit_vector();
-        if scatter_direction.near_zero() {
-            scatter_direction = normal;
-        }
-        ScatterResult::Scattered {
-            scatter_direction,
-            attenuation: self.albedo,
+    /// Directions are picked with a density proportional to the cosine of their angle to the
+    /// normal, which cancels out the cosine in eval, leaving just the albedo.
+    fn sample(
+        &self,
+        ray_in: &Ray,
+        point: &SurfacePoint,
+        sampler: &mut Sampler,
+    ) -> Option<BsdfSample> {
+        let mut direction = point.normal + Vec3::random_unit_vector(sampler);
+        if direction.near_zero() {
+            direction = point.normal;
         }
+        Some(BsdfSample {
+            direction,
+            weight: self.albedo.value(point.u, point.v, point.p),
+            pdf: self.pdf(ray_in, point, direction),
+            delta: false,
+        })
+    }
+
+    fn eval(&self, _ray_in: &Ray, point: &SurfacePoint, direction: Vec3) -> Vec3 {
+        let cosine = point.normal.dot(&direction.normalize()).max(0.0);
+        cosine / PI * self.albedo.value(point.u, point.v, point.p)
+    }
+
+    fn pdf(&self, _ray_in: &Ray, point: &SurfacePoint, direction: Vec3) -> f64 {
+        point.normal.dot(&direction.normalize()).max(0.0) / PI
+    }
+
+    fn albedo(&self, point: &SurfacePoint) -> Vec3 {
+        self.albedo.value(point.u, point.v, point.p)
     }
 }
@@ -1,14 +1,26 @@
-use crate::material::{reflect, Material, ScatterResult};
+use crate::hit::SurfacePoint;
+use crate::material::{reflect, BsdfSample, Material};
 use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::texture::solid_color::SolidColor;
+use crate::texture::Texture;
 use crate::vec3::Vec3;
 
+/// A shiny Material which reflects light like a mirror, blurred by its fuzz.
 pub struct Metal {
-    albedo: Vec3,
+    albedo: Box<dyn Texture>,
     fuzz: f64,
 }
 
 impl Metal {
+    /// Create a metal of a single color. Fuzz runs from 0 for a perfect mirror to 1 for a very
+    /// rough surface.
     pub fn make(albedo: Vec3, fuzz: f64) -> Metal {
+        Metal::textured(Box::from(SolidColor::make(albedo)), fuzz)
+    }
+
+    /// Create a metal whose color is given by a texture.
+    pub fn textured(albedo: Box<dyn Texture>, fuzz: f64) -> Metal {
         Metal {
             albedo,
             fuzz: fuzz.clamp(0.0, 1.0),
@@ -17,18 +29,32 @@ impl Metal {
 }
 
 impl Material for Metal {
-    fn scatter(&self, ray_in: &Ray, normal: Vec3, _front_face: bool) -> ScatterResult {
-        let scatter_direction = reflect(ray_in, normal, self.fuzz);
+    /// A single delta lobe, reflecting like a mirror. Fuzz nudges the reflection in a random
+    /// direction, but it is still treated as a delta lobe, as the density of the nudged
+    /// directions isn't known.
+    fn sample(
+        &self,
+        ray_in: &Ray,
+        point: &SurfacePoint,
+        sampler: &mut Sampler,
+    ) -> Option<BsdfSample> {
+        let direction = reflect(ray_in, point.normal, self.fuzz, sampler);
         // If the reflected ray is pointing out, then the ray is reflected
-        if scatter_direction.dot(&normal) > 0.0 {
-            ScatterResult::Scattered {
-                scatter_direction,
-                attenuation: self.albedo,
-            }
+        if direction.dot(&point.normal) > 0.0 {
+            Some(BsdfSample {
+                direction,
+                weight: self.albedo.value(point.u, point.v, point.p),
+                pdf: 1.0,
+                delta: true,
+            })
         }
         // otherwise the ray is absorbed
         else {
-            ScatterResult::Absorbed {}
+            None
         }
     }
+
+    fn albedo(&self, point: &SurfacePoint) -> Vec3 {
+        self.albedo.value(point.u, point.v, point.p)
+    }
 }
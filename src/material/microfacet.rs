@@ -0,0 +1,261 @@
+use std::f64::consts::PI;
+
+use crate::vec3::Vec3;
+
+/// Roughnesses below this are treated as perfectly smooth, with delta lobes, since the GGX
+/// distribution becomes too narrow to sample or evaluate reliably.
+pub const SMOOTH_ALPHA: f64 = 1e-3;
+
+/// An orthonormal basis around a surface normal. Directions in the basis are local, with the
+/// normal along z, which is where the microfacet formulas are simplest.
+pub struct Frame {
+    s: Vec3,
+    t: Vec3,
+    n: Vec3,
+}
+
+impl Frame {
+    /// A basis with z along the unit normal n, using the branchless construction of Duff et al.,
+    /// "Building an Orthonormal Basis, Revisited".
+    pub fn make(n: Vec3) -> Frame {
+        let sign = 1f64.copysign(n.z);
+        let a = -1.0 / (sign + n.z);
+        let b = n.x * n.y * a;
+        Frame {
+            s: Vec3 {
+                x: 1.0 + sign * n.x * n.x * a,
+                y: sign * b,
+                z: -sign * n.x,
+            },
+            t: Vec3 {
+                x: b,
+                y: sign + n.y * n.y * a,
+                z: -n.y,
+            },
+            n,
+        }
+    }
+
+    /// Turn a direction in the world into one in the basis.
+    pub fn to_local(&self, v: Vec3) -> Vec3 {
+        Vec3 {
+            x: v.dot(&self.s),
+            y: v.dot(&self.t),
+            z: v.dot(&self.n),
+        }
+    }
+
+    /// Turn a direction in the basis back into one in the world.
+    pub fn to_world(&self, v: Vec3) -> Vec3 {
+        v.x * self.s + v.y * self.t + v.z * self.n
+    }
+}
+
+/// The GGX (Trowbridge-Reitz) distribution of microfacet normals, for a rough surface made of
+/// tiny perfect mirrors. All directions are local, pointing away from the surface.
+pub struct Ggx {
+    alpha: f64,
+}
+
+impl Ggx {
+    /// The distribution for a perceptual roughness from 0 (smooth) to 1 (very rough). Squaring
+    /// the roughness gives alpha, the slope of the distribution, which looks far more even.
+    pub fn from_roughness(roughness: f64) -> Ggx {
+        let roughness = roughness.clamp(0.0, 1.0);
+        Ggx {
+            alpha: roughness * roughness,
+        }
+    }
+
+    /// Whether the surface is smooth enough to be treated as a perfect mirror.
+    pub fn is_smooth(&self) -> bool {
+        self.alpha < SMOOTH_ALPHA
+    }
+
+    /// The density of microfacets facing along h, per unit area of the surface.
+    pub fn d(&self, h: Vec3) -> f64 {
+        if h.z <= 0.0 {
+            return 0.0;
+        }
+        let alpha2 = self.alpha * self.alpha;
+        let t = (h.x * h.x + h.y * h.y) / alpha2 + h.z * h.z;
+        1.0 / (PI * alpha2 * t * t)
+    }
+
+    /// Smith's auxiliary function: the area of microfacets hidden from w, relative to the area
+    /// seen.
+    fn lambda(&self, w: Vec3) -> f64 {
+        let tan2_theta = (w.x * w.x + w.y * w.y) / (w.z * w.z);
+        if !tan2_theta.is_finite() {
+            return f64::INFINITY;
+        }
+        ((1.0 + self.alpha * self.alpha * tan2_theta).sqrt() - 1.0) / 2.0
+    }
+
+    /// The fraction of microfacets seen from w that aren't hidden by others.
+    pub fn g1(&self, w: Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// The fraction of microfacets seen from both wo and wi, with the height-correlated Smith
+    /// shadowing-masking function.
+    pub fn g(&self, wo: Vec3, wi: Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// The density of microfacet normals seen from wo, which must be above the surface.
+    pub fn visible_d(&self, wo: Vec3, h: Vec3) -> f64 {
+        self.g1(wo) / wo.z * self.d(h) * wo.dot(&h).max(0.0)
+    }
+
+    /// Pick a microfacet normal in proportion to how much of it is seen from wo, from two random
+    /// numbers between 0 and 1. This is Eric Heitz's "Sampling the GGX Distribution of Visible
+    /// Normals", which never picks normals facing away from wo.
+    pub fn sample_visible(&self, wo: Vec3, u1: f64, u2: f64) -> Vec3 {
+        // Stretch the view direction so the distribution becomes a hemisphere.
+        let vh = Vec3 {
+            x: self.alpha * wo.x,
+            y: self.alpha * wo.y,
+            z: wo.z,
+        }
+        .normalize();
+        let length_squared = vh.x * vh.x + vh.y * vh.y;
+        let t1 = if length_squared > 0.0 {
+            Vec3 {
+                x: -vh.y,
+                y: vh.x,
+                z: 0.0,
+            } / length_squared.sqrt()
+        } else {
+            Vec3 {
+                x: 1.0,
+                y: 0.0,
+                z: 0.0,
+            }
+        };
+        let t2 = vh.cross(&t1);
+
+        // Pick a point on the disk the hemisphere projects to, squashed where it is hidden.
+        let r = u1.sqrt();
+        let phi = 2.0 * PI * u2;
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + vh.z);
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+        let nh = p1 * t1 + p2 * t2 + (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt() * vh;
+
+        // Unstretch the normal.
+        Vec3 {
+            x: self.alpha * nh.x,
+            y: self.alpha * nh.y,
+            z: nh.z.max(1e-6),
+        }
+        .normalize()
+    }
+}
+
+/// Reflect w about the unit normal n. Both point away from the surface.
+pub fn reflect(w: Vec3, n: Vec3) -> Vec3 {
+    2.0 * w.dot(&n) * n - w
+}
+
+/// Refract w through a surface with unit normal n on the same side as w, where eta is the index
+/// of refraction of the far side over that of w's side. Returns None for total internal
+/// reflection.
+pub fn refract(w: Vec3, n: Vec3, eta: f64) -> Option<Vec3> {
+    let cos_i = w.dot(&n);
+    let sin2_t = (1.0 - cos_i * cos_i).max(0.0) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some(-w / eta + (cos_i / eta - cos_t) * n)
+}
+
+/// The fraction of unpolarised light reflected by a smooth boundary between clear materials,
+/// arriving at an angle with cosine cos_i, where eta is the index of refraction of the far side
+/// over that of the near side. The rest is refracted, unless it is all reflected by total
+/// internal reflection.
+pub fn fresnel_dielectric(cos_i: f64, eta: f64) -> f64 {
+    let (cos_i, eta) = if cos_i < 0.0 {
+        (-cos_i, 1.0 / eta)
+    } else {
+        (cos_i, eta)
+    };
+    let cos_i = cos_i.min(1.0);
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let r_parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let r_perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    (r_parallel * r_parallel + r_perpendicular * r_perpendicular) / 2.0
+}
+
+/// The fraction of unpolarised light of each color reflected by a metal in air, arriving at an
+/// angle with cosine cos_i. The metal's complex index of refraction is eta + ik, where k, its
+/// absorption, is what makes metals shiny.
+pub fn fresnel_conductor(cos_i: f64, eta: Vec3, k: Vec3) -> Vec3 {
+    let cos_i = cos_i.clamp(0.0, 1.0);
+    let channel = |eta: f64, k: f64| {
+        let cos2 = cos_i * cos_i;
+        let sin2 = 1.0 - cos2;
+        let t0 = eta * eta - k * k - sin2;
+        let a2_plus_b2 = (t0 * t0 + 4.0 * eta * eta * k * k).sqrt();
+        let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+        let t1 = a2_plus_b2 + cos2;
+        let t2 = 2.0 * cos_i * a;
+        let r_s = (t1 - t2) / (t1 + t2);
+        let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+        let t4 = t2 * sin2;
+        let r_p = r_s * (t3 - t4) / (t3 + t4);
+        (r_s + r_p) / 2.0
+    };
+    Vec3 {
+        x: channel(eta.x, k.x),
+        y: channel(eta.y, k.y),
+        z: channel(eta.z, k.z),
+    }
+}
+
+#[test]
+fn test_visible_normals_are_sampled_with_their_density() {
+    use crate::sampler::Sampler;
+
+    // Every sampled normal faces the viewer, and the visible normal density integrates to one
+    // over the hemisphere, estimated with directions picked evenly over it.
+    let ggx = Ggx::from_roughness(0.5);
+    let wo = Vec3 {
+        x: 0.6,
+        y: 0.0,
+        z: 0.8,
+    };
+    let mut sampler = Sampler::make(0);
+    for _ in 0..1000 {
+        let h = ggx.sample_visible(wo, sampler.random_double(), sampler.random_double());
+        assert!(h.z > 0.0 && wo.dot(&h) >= 0.0);
+        assert!((h.magnitude() - 1.0).abs() < 1e-9);
+    }
+    let samples = 200000;
+    let total: f64 = (0..samples)
+        .map(|_| {
+            let h = Vec3::random_unit_vector(&mut sampler);
+            ggx.visible_d(wo, Vec3 { z: h.z.abs(), ..h })
+        })
+        .sum();
+    assert!((total * 2.0 * PI / samples as f64 - 1.0).abs() < 0.02);
+}
+
+#[test]
+fn test_fresnel_matches_known_values() {
+    // Glass reflects 4% head on, and everything at grazing angles and beyond the critical angle.
+    assert!((fresnel_dielectric(1.0, 1.5) - 0.04).abs() < 1e-12);
+    assert!((fresnel_dielectric(0.0, 1.5) - 1.0).abs() < 1e-12);
+    assert_eq!(1.0, fresnel_dielectric(-0.5, 1.5));
+    // A metal with no absorption is just a dielectric.
+    let conductor = fresnel_conductor(0.7, Vec3::from_one(1.5), Vec3::zero());
+    assert!((conductor.x - fresnel_dielectric(0.7, 1.5)).abs() < 1e-12);
+    // Head on, a conductor reflects ((eta - 1)^2 + k^2) / ((eta + 1)^2 + k^2).
+    let conductor = fresnel_conductor(1.0, Vec3::from_one(0.2), Vec3::from_one(3.0));
+    assert!((conductor.x - (0.64 + 9.0) / (1.44 + 9.0)).abs() < 1e-12);
+}
@@ -1,28 +1,71 @@
-use crate::material::{Material, ScatterResult};
+use std::f64::consts::PI;
+
+use crate::hit::SurfacePoint;
+use crate::material::{BsdfSample, Material};
 use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::texture::solid_color::SolidColor;
+use crate::texture::Texture;
 use crate::vec3::Vec3;
 
+/// A matte Material which scatters light equally in every direction away from the surface.
 pub struct UniformScatterer {
-    albedo: Vec3,
+    albedo: Box<dyn Texture>,
 }
 
 impl UniformScatterer {
+    /// Create a uniform scatterer of a single color.
     pub fn make(albedo: Vec3) -> UniformScatterer {
+        UniformScatterer::textured(Box::from(SolidColor::make(albedo)))
+    }
+
+    /// Create a uniform scatterer whose color is given by a texture.
+    pub fn textured(albedo: Box<dyn Texture>) -> UniformScatterer {
         UniformScatterer { albedo }
     }
 }
 
 impl Material for UniformScatterer {
-    fn scatter(&self, _ray_in: &Ray, normal: Vec3, _front_face: bool) -> ScatterResult {
-        let mut scatter_direction = Vec3::random_in_hemisphere(&normal);
+    /// Every direction on the outside of the surface is equally likely.
+    fn sample(
+        &self,
+        ray_in: &Ray,
+        point: &SurfacePoint,
+        sampler: &mut Sampler,
+    ) -> Option<BsdfSample> {
+        let mut direction = Vec3::random_in_hemisphere(sampler, &point.normal);
 
         // Prevent scatter direction being the zero vector, which can lead to infinities/NaNs.
-        if scatter_direction.near_zero() {
-            scatter_direction = normal;
+        if direction.near_zero() {
+            direction = point.normal;
+        }
+        Some(BsdfSample {
+            direction,
+            weight: self.albedo.value(point.u, point.v, point.p),
+            pdf: self.pdf(ray_in, point, direction),
+            delta: false,
+        })
+    }
+
+    /// The same for every direction outside the surface, so that each direction sampled carries
+    /// back exactly the albedo.
+    fn eval(&self, _ray_in: &Ray, point: &SurfacePoint, direction: Vec3) -> Vec3 {
+        if point.normal.dot(&direction) > 0.0 {
+            self.albedo.value(point.u, point.v, point.p) / (2.0 * PI)
+        } else {
+            Vec3::zero()
         }
-        ScatterResult::Scattered {
-            scatter_direction,
-            attenuation: self.albedo,
+    }
+
+    fn pdf(&self, _ray_in: &Ray, point: &SurfacePoint, direction: Vec3) -> f64 {
+        if point.normal.dot(&direction) > 0.0 {
+            1.0 / (2.0 * PI)
+        } else {
+            0.0
         }
     }
+
+    fn albedo(&self, point: &SurfacePoint) -> Vec3 {
+        self.albedo.value(point.u, point.v, point.p)
+    }
 }
@@ -0,0 +1,303 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hit::{HitOrMiss, Hittable};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+
+/// Media whose density varies, given by voxel grids.
+pub mod grid;
+
+pub use grid::GridMedium;
+
+/// The most times a ray is followed in and out of a boundary, in case a broken mesh or rounding
+/// keeps it crossing the same place.
+const MAX_CROSSINGS: usize = 64;
+
+/// A participating medium, like fog or smoke, filling a volume. Rays passing through it may be
+/// scattered by particles anywhere inside rather than only at surfaces, and light passing
+/// through it is dimmed.
+///
+/// Media aren't hit like surfaces. Integrators find the nearest surface a ray hits, then ask each
+/// medium whether the ray is scattered before reaching it. A scattering is a hit on a point in
+/// the medium, made of the medium's phase function, so the integrators can scatter and sample
+/// the lights from it just as at a surface.
+///
+/// Media are shared between render threads, so must be Send and Sync.
+pub trait Medium: Send + Sync {
+    /// Find where the ray is scattered by the medium between t_min and t_max along it, drawing
+    /// any random numbers needed from the sampler, or Miss if it passes through.
+    fn sample(&self, ray: &Ray, t_min: f64, t_max: f64, sampler: &mut Sampler) -> HitOrMiss<'_>;
+
+    /// The fraction of light travelling along the ray between t_min and t_max that makes it
+    /// through the medium.
+    fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64, sampler: &mut Sampler) -> f64;
+
+    /// The smallest axis-aligned box enclosing the medium, or None if it is unbounded.
+    fn bounding_box(&self) -> Option<Aabb>;
+}
+
+/// A medium of the same density everywhere inside a closed boundary, such as a sphere or box of
+/// fog. The chance of a ray being scattered is the same for every unit of distance it travels
+/// inside, so how far it gets is picked exactly from an exponential distribution rather than by
+/// marching along it.
+///
+/// The boundary may be any closed Hittable, convex or not, and need not be in the world itself:
+/// it is only used to find where rays are inside.
+pub struct ConstantMedium {
+    boundary: Box<dyn Hittable>,
+    density: f64,
+    phase: Box<dyn Material>,
+}
+
+impl ConstantMedium {
+    /// Fill a boundary with a medium of the given density, the chance of a ray being scattered
+    /// per unit of distance, scattering light by the given phase function, such as Isotropic.
+    pub fn make(
+        boundary: Box<dyn Hittable>,
+        density: f64,
+        phase: Box<dyn Material>,
+    ) -> ConstantMedium {
+        ConstantMedium {
+            boundary,
+            density,
+            phase,
+        }
+    }
+}
+
+impl Medium for ConstantMedium {
+    fn sample(&self, ray: &Ray, t_min: f64, t_max: f64, sampler: &mut Sampler) -> HitOrMiss<'_> {
+        let spans = spans_inside(&*self.boundary, ray, t_min, t_max);
+        if spans.is_empty() || self.density <= 0.0 {
+            return HitOrMiss::Miss;
+        }
+        let length = ray.direction.magnitude();
+        // How far the ray gets inside the boundary before being scattered, used up span by span.
+        let mut distance = -(1.0 - sampler.random_double()).ln() / self.density;
+        for (start, end) in spans {
+            let span = (end - start) * length;
+            if distance < span {
+                let t = start + distance / length;
+                return scattering(ray, t, &*self.phase);
+            }
+            distance -= span;
+        }
+        HitOrMiss::Miss
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64, _sampler: &mut Sampler) -> f64 {
+        let length = ray.direction.magnitude();
+        let inside: f64 = spans_inside(&*self.boundary, ray, t_min, t_max)
+            .iter()
+            .map(|(start, end)| (end - start) * length)
+            .sum();
+        (-self.density * inside).exp()
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.boundary.bounding_box()
+    }
+}
+
+/// A scattering at t along the ray, in a medium with the given phase function. There is no
+/// surface there, so the normal just faces back along the ray.
+pub fn scattering<'a>(ray: &Ray, t: f64, phase: &'a dyn Material) -> HitOrMiss<'a> {
+    HitOrMiss::hit(
+        ray.at(t),
+        -ray.direction.normalize(),
+        t,
+        (0.0, 0.0),
+        ray,
+        phase,
+    )
+}
+
+/// The stretches of the ray between t_min and t_max that are inside a closed boundary, in
+/// order. The ray is followed through the boundary from far behind its start, entering through
+/// front faces and leaving through back faces, so the ray may start inside and the boundary need
+/// not be convex.
+pub fn spans_inside(boundary: &dyn Hittable, ray: &Ray, t_min: f64, t_max: f64) -> Vec<(f64, f64)> {
+    let mut spans = vec![];
+    let mut entered = None;
+    let mut t = f64::NEG_INFINITY;
+    for _ in 0..MAX_CROSSINGS {
+        match boundary.hit(ray, t, f64::INFINITY) {
+            HitOrMiss::Hit {
+                t: crossing, point, ..
+            } => {
+                if point.front_face {
+                    entered = Some(crossing);
+                } else if let Some(start) = entered.take() {
+                    spans.push((start, crossing));
+                }
+                if crossing >= t_max {
+                    break;
+                }
+                // Only just past the crossing, so that leaving straight after entering, as past
+                // the edge of a box, isn't missed. The same crossing is found at exactly the
+                // same place each time, so isn't found again.
+                t = crossing + f64::EPSILON * crossing.abs().max(1.0);
+            }
+            HitOrMiss::Miss => {
+                // A ray can't really stay inside a bounded boundary, so must have slipped out
+                // unseen, through an edge or a gap in a mesh.
+                if boundary.bounding_box().is_some() {
+                    entered = None;
+                }
+                break;
+            }
+        }
+    }
+    // A ray that never leaves an unbounded boundary is inside from then on, as is one that
+    // is still inside when we stop looking.
+    if let Some(start) = entered {
+        spans.push((start, f64::INFINITY));
+    }
+    spans
+        .into_iter()
+        .map(|(start, end)| (start.max(t_min), end.min(t_max)))
+        .filter(|(start, end)| start < end)
+        .collect()
+}
+
+/// A Hittable holding a Medium, to place it in the world among the other objects. Rays never hit
+/// it: the World finds it with the other media and samples it along each ray instead. Its
+/// boundary isn't drawn either, so to put fog inside a glass ball, add the ball as well.
+///
+/// Instances can't move or turn a Volume; transform the medium's boundary instead.
+pub struct Volume {
+    medium: Arc<dyn Medium>,
+}
+
+impl Volume {
+    /// Place a medium in the world.
+    pub fn make(medium: Box<dyn Medium>) -> Volume {
+        Volume {
+            medium: Arc::from(medium),
+        }
+    }
+}
+
+impl Hittable for Volume {
+    fn hit(&self, _ray: &Ray, _t_min: f64, _t_max: f64) -> HitOrMiss<'_> {
+        HitOrMiss::Miss
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.medium.bounding_box()
+    }
+
+    fn media(&self) -> Vec<Arc<dyn Medium>> {
+        vec![self.medium.clone()]
+    }
+}
+
+#[test]
+fn test_spans_inside_follow_the_ray_in_and_out() {
+    use crate::hit::HittableList;
+    use crate::material::Isotropic;
+    use crate::sphere::Sphere;
+    use crate::vec3::Vec3;
+
+    // Two balls in a row along the x axis, which together are not convex.
+    let ball = |x| {
+        Box::from(Sphere {
+            center: Vec3 { x, y: 0.0, z: 0.0 },
+            r: 1.0,
+            material: Box::from(Isotropic::make(Vec3::from_one(1.0))),
+        }) as Box<dyn Hittable>
+    };
+    let boundary = HittableList {
+        hittables: vec![ball(0.0), ball(4.0)],
+    };
+    let ray = Ray {
+        origin: Vec3 {
+            x: -5.0,
+            y: 0.0,
+            z: 0.0,
+        },
+        direction: Vec3 {
+            x: 2.0,
+            y: 0.0,
+            z: 0.0,
+        },
+        time: 0.0,
+    };
+    let close = |spans: Vec<(f64, f64)>, expected: &[(f64, f64)]| {
+        spans.len() == expected.len()
+            && spans
+                .iter()
+                .zip(expected)
+                .all(|(a, b)| (a.0 - b.0).abs() < 1e-9 && (a.1 - b.1).abs() < 1e-9)
+    };
+    assert!(close(
+        spans_inside(&boundary, &ray, 0.0, f64::INFINITY),
+        &[(2.0, 3.0), (4.0, 5.0)]
+    ));
+    // Starting inside the first ball, and stopping inside the second.
+    assert!(close(
+        spans_inside(&boundary, &ray, 2.5, 4.5),
+        &[(2.5, 3.0), (4.0, 4.5)]
+    ));
+    assert!(spans_inside(&boundary, &ray, 3.2, 3.8).is_empty());
+}
+
+#[test]
+fn test_constant_medium_scatters_consistently_with_its_transmittance() {
+    use crate::cuboid::Cuboid;
+    use crate::material::Isotropic;
+    use crate::vec3::Vec3;
+
+    // A slab of fog 2 thick, crossed by rays at an angle.
+    let medium = ConstantMedium::make(
+        Box::from(Cuboid::make(
+            Vec3 {
+                x: -10.0,
+                y: -1.0,
+                z: -10.0,
+            },
+            Vec3 {
+                x: 10.0,
+                y: 1.0,
+                z: 10.0,
+            },
+            Box::from(Isotropic::make(Vec3::from_one(1.0))),
+        )),
+        0.4,
+        Box::from(Isotropic::make(Vec3::from_one(0.8))),
+    );
+    let ray = Ray {
+        origin: Vec3 {
+            x: 0.0,
+            y: 3.0,
+            z: 0.0,
+        },
+        direction: Vec3 {
+            x: 0.5,
+            y: -1.0,
+            z: 0.0,
+        },
+        time: 0.0,
+    };
+    let mut sampler = Sampler::make(0);
+    // The path through the slab is 2 * sqrt(1.25) long.
+    let transmittance = medium.transmittance(&ray, 0.001, f64::INFINITY, &mut sampler);
+    assert!((transmittance - (-0.4 * 2.0 * 1.25f64.sqrt()).exp()).abs() < 1e-6);
+
+    let samples = 100000;
+    let mut passed = 0;
+    for _ in 0..samples {
+        match medium.sample(&ray, 0.001, f64::INFINITY, &mut sampler) {
+            HitOrMiss::Hit { point, .. } => assert!(point.p.y.abs() <= 1.0 + 1e-9),
+            HitOrMiss::Miss => passed += 1,
+        }
+    }
+    assert!((passed as f64 / samples as f64 - transmittance).abs() < 0.005);
+    // Nothing is scattered before the ray reaches the slab.
+    assert!(matches!(
+        medium.sample(&ray, 0.001, 1.5, &mut sampler),
+        HitOrMiss::Miss
+    ));
+}
@@ -0,0 +1,261 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hit::{HitOrMiss, Hittable, SurfacePoint};
+use crate::material::{BsdfSample, Material};
+use crate::medium::{scattering, spans_inside, Medium};
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::transform::Transform;
+use crate::vec3::Vec3;
+use crate::voxel::VoxelGrid;
+
+/// A medium whose density varies from place to place, given by a voxel grid, like a cloud or a
+/// plume of smoke. It may also glow, like fire, with light given off by a second grid.
+///
+/// Rays are followed through it by delta tracking: steps are picked as if the whole medium were
+/// as dense as its densest voxel, and at each step the ray is only really scattered with the
+/// chance of the density there over the largest. The steps where it isn't make up the
+/// difference exactly, so the result is unbiased however coarse the grid. Shadow rays use ratio
+/// tracking, taking the same steps but multiplying together the chances of getting past each
+/// one rather than picking whether they do.
+///
+/// The medium is its own material at the points rays are scattered: it scatters them by its
+/// phase function, and gives off its glow there. Glowing media aren't sampled as lights, so
+/// their light is only found by rays scattered inside them.
+pub struct GridMedium {
+    boundary: Box<dyn Hittable>,
+    to_grid: Transform,
+    density: Arc<VoxelGrid>,
+    scale: f64,
+    phase: Box<dyn Material>,
+    emission: Option<(Arc<VoxelGrid>, Vec3)>,
+}
+
+impl GridMedium {
+    /// Fill a boundary with a medium whose density is scale times the value of the grid, found
+    /// by moving points in the world into the grid's unit cube with to_grid. The medium scatters
+    /// light by the given phase function, such as Isotropic. The density is zero outside the
+    /// grid, so the boundary is usually the box the grid fills, and must be bounded.
+    pub fn make(
+        boundary: Box<dyn Hittable>,
+        to_grid: Transform,
+        density: Arc<VoxelGrid>,
+        scale: f64,
+        phase: Box<dyn Material>,
+    ) -> GridMedium {
+        GridMedium {
+            boundary,
+            to_grid,
+            density,
+            scale,
+            phase,
+            emission: None,
+        }
+    }
+
+    /// Make the medium glow, giving off color times the value of the emission grid in light per
+    /// unit of distance along a ray. The emission grid is placed like the density grid, and may
+    /// be the same grid. Only places with some density can glow.
+    pub fn with_emission(self, emission: Arc<VoxelGrid>, color: Vec3) -> GridMedium {
+        GridMedium {
+            emission: Some((emission, color)),
+            ..self
+        }
+    }
+
+    /// The density at a point in the world.
+    fn density_at(&self, p: Vec3) -> f64 {
+        self.scale * self.density.value(self.to_grid.point(p))
+    }
+
+    /// The density no point in the medium is above, which steps are picked with.
+    fn majorant(&self) -> f64 {
+        self.scale * self.density.max()
+    }
+
+    /// Call step with the distance along the ray of each step taken by delta tracking through
+    /// the medium between t_min and t_max, until it returns false.
+    fn track(
+        &self,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+        sampler: &mut Sampler,
+        mut step: impl FnMut(f64, &mut Sampler) -> bool,
+    ) {
+        let majorant = self.majorant();
+        if majorant <= 0.0 {
+            return;
+        }
+        let length = ray.direction.magnitude();
+        for (start, end) in spans_inside(&*self.boundary, ray, t_min, t_max) {
+            let mut t = start;
+            loop {
+                t -= (1.0 - sampler.random_double()).ln() / (majorant * length);
+                if t >= end || !step(t, sampler) {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+impl Medium for GridMedium {
+    fn sample(&self, ray: &Ray, t_min: f64, t_max: f64, sampler: &mut Sampler) -> HitOrMiss<'_> {
+        let majorant = self.majorant();
+        let mut scattered = None;
+        self.track(ray, t_min, t_max, sampler, |t, sampler| {
+            if sampler.random_double() * majorant < self.density_at(ray.at(t)) {
+                scattered = Some(t);
+            }
+            scattered.is_none()
+        });
+        match scattered {
+            Some(t) => scattering(ray, t, self),
+            None => HitOrMiss::Miss,
+        }
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64, sampler: &mut Sampler) -> f64 {
+        let majorant = self.majorant();
+        let mut transmittance = 1.0;
+        self.track(ray, t_min, t_max, sampler, |t, _| {
+            transmittance *= 1.0 - self.density_at(ray.at(t)) / majorant;
+            transmittance > 0.0
+        });
+        transmittance
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.boundary.bounding_box()
+    }
+}
+
+impl Material for GridMedium {
+    fn sample(
+        &self,
+        ray_in: &Ray,
+        point: &SurfacePoint,
+        sampler: &mut Sampler,
+    ) -> Option<BsdfSample> {
+        self.phase.sample(ray_in, point, sampler)
+    }
+
+    fn eval(&self, ray_in: &Ray, point: &SurfacePoint, direction: Vec3) -> Vec3 {
+        self.phase.eval(ray_in, point, direction)
+    }
+
+    fn pdf(&self, ray_in: &Ray, point: &SurfacePoint, direction: Vec3) -> f64 {
+        self.phase.pdf(ray_in, point, direction)
+    }
+
+    /// The light given off per unit distance over the density. Rays are scattered in proportion
+    /// to the density, so on average this adds up to all the light given off along them.
+    fn emitted(&self, point: &SurfacePoint) -> Vec3 {
+        let density = self.density_at(point.p);
+        match &self.emission {
+            Some((emission, color)) if density > 0.0 => {
+                emission.value(self.to_grid.point(point.p)) / density * *color
+            }
+            _ => Vec3::zero(),
+        }
+    }
+
+    fn albedo(&self, point: &SurfacePoint) -> Vec3 {
+        self.phase.albedo(point)
+    }
+}
+
+#[cfg(test)]
+fn unit_box() -> Box<dyn Hittable> {
+    use crate::cuboid::Cuboid;
+    use crate::material::Isotropic;
+
+    Box::from(Cuboid::make(
+        Vec3::zero(),
+        Vec3::from_one(1.0),
+        Box::from(Isotropic::make(Vec3::from_one(1.0))),
+    ))
+}
+
+#[cfg(test)]
+fn ray_along_x() -> Ray {
+    Ray {
+        origin: Vec3 {
+            x: -1.0,
+            y: 0.4,
+            z: 0.6,
+        },
+        direction: Vec3 {
+            x: 0.5,
+            y: 0.0,
+            z: 0.0,
+        },
+        time: 0.0,
+    }
+}
+
+#[test]
+fn test_delta_and_ratio_tracking_match_the_exact_transmittance() {
+    use crate::material::Isotropic;
+
+    // Zero on the left quarter of the box, one on the right quarter, and a ramp in between, so
+    // a ray across it passes through half the density of the right.
+    let scale = 3.0;
+    let medium = GridMedium::make(
+        unit_box(),
+        Transform::identity(),
+        Arc::new(VoxelGrid::make([2, 1, 1], vec![0.0, 1.0])),
+        scale,
+        Box::from(Isotropic::make(Vec3::from_one(1.0))),
+    );
+    let exact = (-scale * 0.5f64).exp();
+    let ray = ray_along_x();
+    let mut sampler = Sampler::make(0);
+    let samples = 100000;
+    let mut passed = 0;
+    let mut total = 0.0;
+    for _ in 0..samples {
+        match Medium::sample(&medium, &ray, 0.001, f64::INFINITY, &mut sampler) {
+            HitOrMiss::Hit { point, .. } => assert!(point.p.x > 0.25 - 1e-9),
+            HitOrMiss::Miss => passed += 1,
+        }
+        total += medium.transmittance(&ray, 0.001, f64::INFINITY, &mut sampler);
+    }
+    assert!((passed as f64 / samples as f64 - exact).abs() < 0.005);
+    assert!((total / samples as f64 - exact).abs() < 0.005);
+}
+
+#[test]
+fn test_glowing_medium_gives_off_its_light_on_average() {
+    use crate::material::Isotropic;
+
+    // Evenly dense and glowing, and absorbing everything, the light leaving along a ray across
+    // the box is the glow per unit distance integrated against the transmittance.
+    let (density, glow) = (2.0, 0.7);
+    let grid = Arc::new(VoxelGrid::make([1, 1, 1], vec![1.0]));
+    let medium = GridMedium::make(
+        unit_box(),
+        Transform::identity(),
+        grid.clone(),
+        density,
+        Box::from(Isotropic::make(Vec3::zero())),
+    )
+    .with_emission(grid, Vec3::from_one(glow));
+    let exact = glow / density * (1.0 - (-density).exp());
+    let ray = ray_along_x();
+    let mut sampler = Sampler::make(0);
+    let samples = 100000;
+    let total: f64 = (0..samples)
+        .map(
+            |_| match Medium::sample(&medium, &ray, 0.001, f64::INFINITY, &mut sampler) {
+                HitOrMiss::Hit {
+                    point, material, ..
+                } => material.emitted(&point).x,
+                HitOrMiss::Miss => 0.0,
+            },
+        )
+        .sum();
+    assert!((total / samples as f64 - exact).abs() < 0.005);
+}
@@ -0,0 +1,96 @@
+use crate::aabb::Aabb;
+use crate::hit::{HitOrMiss, Hittable};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::sphere::Sphere;
+use crate::vec3::Vec3;
+
+/// A Sphere moving in a straight line, from center0 at time 0 to center1 at time 1. It stays
+/// put before and after, so its bounding box covers every place it can be.
+///
+/// Moving spheres that give off light aren't sampled as lights, since lights are the same at
+/// every moment. Rays still find their light by bouncing into them.
+pub struct MovingSphere {
+    /// The center of the sphere at time 0.
+    pub center0: Vec3,
+    /// The center of the sphere at time 1.
+    pub center1: Vec3,
+    /// The radius of the sphere.
+    pub r: f64,
+    /// What the sphere is made of.
+    pub material: Box<dyn Material>,
+}
+
+impl MovingSphere {
+    /// The center of the sphere at the given time.
+    pub fn center(&self, time: f64) -> Vec3 {
+        let time = time.clamp(0.0, 1.0);
+        self.center0 + time * (self.center1 - self.center0)
+    }
+}
+
+impl Hittable for MovingSphere {
+    /// Test whether a given ray hit the sphere where it was when the ray was sent.
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> HitOrMiss<'_> {
+        Sphere::hit_at(
+            self.center(ray.time),
+            self.r,
+            &*self.material,
+            ray,
+            t_min,
+            t_max,
+        )
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let radius = Vec3::from_one(self.r.abs());
+        let box_at = |center: Vec3| Aabb {
+            min: center - radius,
+            max: center + radius,
+        };
+        Some(Aabb::surrounding(
+            &box_at(self.center0),
+            &box_at(self.center1),
+        ))
+    }
+}
+
+#[test]
+fn test_moving_sphere_is_hit_where_it_was_when_the_ray_was_sent() {
+    use crate::material::lambertian::Lambertian;
+
+    let sphere = MovingSphere {
+        center0: Vec3::zero(),
+        center1: Vec3 {
+            x: 4.0,
+            y: 0.0,
+            z: 0.0,
+        },
+        r: 1.0,
+        material: Box::from(Lambertian::make(Vec3::from_one(0.5))),
+    };
+    let ray_at = |x: f64, time: f64| Ray {
+        origin: Vec3 { x, y: 0.0, z: 5.0 },
+        direction: Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: -1.0,
+        },
+        time,
+    };
+    let hits = |ray: Ray| {
+        matches!(
+            sphere.hit(&ray, 0.001, f64::INFINITY),
+            HitOrMiss::Hit { .. }
+        )
+    };
+    assert!(hits(ray_at(0.0, 0.0)));
+    assert!(!hits(ray_at(0.0, 1.0)));
+    assert!(hits(ray_at(2.0, 0.5)));
+    assert!(hits(ray_at(4.0, 1.0)));
+    // It stops at the end of its path.
+    assert!(hits(ray_at(4.0, 2.0)));
+
+    let bbox = sphere.bounding_box().unwrap();
+    assert_eq!((-1.0, 5.0), (bbox.min.x, bbox.max.x));
+}
@@ -0,0 +1,324 @@
+//! Loading of Wavefront OBJ models, along with the MTL material libraries they refer to.
+//!
+//! Only the parts of the format describing polygonal geometry are supported: vertex positions
+//! (`v`), normals (`vn`), texture coordinates (`vt`), faces (`f`) and materials (`mtllib` and
+//! `usemtl`). Faces with more than three corners are split into a fan of triangles. Anything
+//! else (groups, smoothing, curves) is ignored. Diffuse texture maps (`map_Kd`) must be PNG or
+//! PPM images.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::SplitWhitespace;
+
+use crate::image::Image;
+use crate::material::dielectric::Dielectric;
+use crate::material::diffuse_light::DiffuseLight;
+use crate::material::lambertian::Lambertian;
+use crate::material::metal::Metal;
+use crate::material::Material;
+use crate::texture::image_texture::ImageTexture;
+use crate::triangle::{Face, TriangleMesh};
+use crate::vec3::Vec3;
+
+/// The reasons loading an OBJ or MTL file can fail.
+#[derive(Debug)]
+pub enum ObjError {
+    /// A file could not be read.
+    Io {
+        /// The file that could not be read.
+        path: PathBuf,
+        /// Why it could not be read.
+        error: io::Error,
+    },
+    /// A file contains something we do not understand.
+    Parse {
+        /// The OBJ or MTL file.
+        path: PathBuf,
+        /// The line of the file the problem is on.
+        line: usize,
+        /// What the problem is.
+        message: String,
+    },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            ObjError::Parse {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", path.display(), line, message),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+/// The properties of an MTL material that we know how to map on to our own materials.
+struct MtlMaterial {
+    /// Diffuse color
+    kd: Vec3,
+    /// Specular color
+    ks: Vec3,
+    /// Specular exponent
+    ns: f64,
+    /// Index of refraction
+    ni: f64,
+    /// Opacity
+    d: f64,
+    /// Illumination model
+    illum: u32,
+    /// Emitted light
+    ke: Vec3,
+    /// Path to an image to use as the diffuse color
+    map_kd: Option<PathBuf>,
+}
+
+impl Default for MtlMaterial {
+    fn default() -> MtlMaterial {
+        MtlMaterial {
+            kd: Vec3::from_one(0.5),
+            ks: Vec3::zero(),
+            ns: 0.0,
+            ni: 1.5,
+            d: 1.0,
+            illum: 2,
+            ke: Vec3::zero(),
+            map_kd: None,
+        }
+    }
+}
+
+impl MtlMaterial {
+    /// Choose the closest of our materials to the MTL description. Materials giving off light
+    /// become DiffuseLight, transparent materials become Dielectric, materials which are more
+    /// specular than diffuse become Metal, and everything else is Lambertian.
+    fn to_material(&self) -> Result<Box<dyn Material>, ObjError> {
+        let max = |v: Vec3| v.x.max(v.y).max(v.z);
+        Ok(if max(self.ke) > 0.0 {
+            Box::from(DiffuseLight::make(self.ke))
+        } else if self.d < 1.0 || matches!(self.illum, 4 | 6 | 7 | 9) {
+            Box::from(Dielectric::make(self.ni))
+        } else if self.illum == 3 || max(self.ks) > max(self.kd) {
+            // Convert the Phong exponent to something like a roughness.
+            let fuzz = (2.0 / (self.ns + 2.0)).sqrt();
+            Box::from(Metal::make(self.ks, fuzz))
+        } else if let Some(path) = &self.map_kd {
+            let image = Image::load(path).map_err(|error| ObjError::Io {
+                path: path.clone(),
+                error,
+            })?;
+            Box::from(Lambertian::textured(Box::from(ImageTexture::make(image))))
+        } else {
+            Box::from(Lambertian::make(self.kd))
+        })
+    }
+}
+
+/// Splits a file into whitespace separated tokens line by line, tracking where we are so errors
+/// can be reported.
+struct Parser<'a> {
+    path: &'a Path,
+    line: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: String) -> ObjError {
+        ObjError::Parse {
+            path: self.path.to_path_buf(),
+            line: self.line,
+            message,
+        }
+    }
+
+    fn float(&self, tokens: &mut SplitWhitespace) -> Result<f64, ObjError> {
+        let token = tokens
+            .next()
+            .ok_or_else(|| self.error("Expected a number".to_string()))?;
+        token
+            .parse()
+            .map_err(|_| self.error(format!("Expected a number but found '{}'", token)))
+    }
+
+    fn vec3(&self, tokens: &mut SplitWhitespace) -> Result<Vec3, ObjError> {
+        Ok(Vec3 {
+            x: self.float(tokens)?,
+            y: self.float(tokens)?,
+            z: self.float(tokens)?,
+        })
+    }
+
+    /// Parse a 1-based, or negative relative, index into a buffer of the given length.
+    fn index(&self, token: &str, len: usize) -> Result<usize, ObjError> {
+        let index: i64 = token
+            .parse()
+            .map_err(|_| self.error(format!("Expected an index but found '{}'", token)))?;
+        let resolved = if index < 0 {
+            len as i64 + index
+        } else {
+            index - 1
+        };
+        if resolved < 0 || resolved >= len as i64 {
+            return Err(self.error(format!(
+                "Index {} is out of range, only {} are defined",
+                index, len
+            )));
+        }
+        Ok(resolved as usize)
+    }
+}
+
+fn read(path: &Path) -> Result<String, ObjError> {
+    fs::read_to_string(path).map_err(|error| ObjError::Io {
+        path: path.to_path_buf(),
+        error,
+    })
+}
+
+/// Load the named materials from an MTL file.
+fn load_mtl(path: &Path) -> Result<HashMap<String, MtlMaterial>, ObjError> {
+    let contents = read(path)?;
+    let mut parser = Parser { path, line: 0 };
+    let mut materials = HashMap::new();
+    let mut current: Option<String> = None;
+    for (line_index, line) in contents.lines().enumerate() {
+        parser.line = line_index + 1;
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) if !keyword.starts_with('#') => keyword,
+            _ => continue,
+        };
+        if keyword == "newmtl" {
+            let name = tokens.collect::<Vec<_>>().join(" ");
+            materials.insert(name.clone(), MtlMaterial::default());
+            current = Some(name);
+            continue;
+        }
+        let material = match current.as_ref().and_then(|name| materials.get_mut(name)) {
+            Some(material) => material,
+            None => continue,
+        };
+        match keyword {
+            "Kd" => material.kd = parser.vec3(&mut tokens)?,
+            "Ks" => material.ks = parser.vec3(&mut tokens)?,
+            "Ns" => material.ns = parser.float(&mut tokens)?,
+            "Ni" => material.ni = parser.float(&mut tokens)?,
+            "d" => material.d = parser.float(&mut tokens)?,
+            "Tr" => material.d = 1.0 - parser.float(&mut tokens)?,
+            "illum" => material.illum = parser.float(&mut tokens)? as u32,
+            "Ke" => material.ke = parser.vec3(&mut tokens)?,
+            "map_Kd" => {
+                // Options may come before the file name, which is always last.
+                let name = tokens
+                    .last()
+                    .ok_or_else(|| parser.error("Expected a file name".to_string()))?;
+                material.map_kd = Some(path.parent().unwrap_or_else(|| Path::new("")).join(name));
+            }
+            _ => {}
+        }
+    }
+    Ok(materials)
+}
+
+/// Load an OBJ file into a TriangleMesh. Materials are read from any MTL libraries it refers to,
+/// relative to the OBJ file. Faces without a material are given a grey Lambertian one.
+pub fn load(path: &Path) -> Result<TriangleMesh, ObjError> {
+    let contents = read(path)?;
+    let mut parser = Parser { path, line: 0 };
+    let mut mesh = TriangleMesh {
+        positions: vec![],
+        normals: vec![],
+        uvs: vec![],
+        faces: vec![],
+        materials: vec![MtlMaterial::default().to_material()?],
+    };
+    let mut library: HashMap<String, MtlMaterial> = HashMap::new();
+    // Materials are only converted and added to the mesh the first time a face uses them.
+    let mut material_indices: HashMap<String, usize> = HashMap::new();
+    let mut current_material = 0;
+
+    for (line_index, line) in contents.lines().enumerate() {
+        parser.line = line_index + 1;
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) if !keyword.starts_with('#') => keyword,
+            _ => continue,
+        };
+        match keyword {
+            "v" => mesh.positions.push(parser.vec3(&mut tokens)?),
+            "vn" => mesh.normals.push(parser.vec3(&mut tokens)?),
+            "vt" => {
+                let u = parser.float(&mut tokens)?;
+                let v = parser.float(&mut tokens)?;
+                mesh.uvs.push((u, v));
+            }
+            "f" => {
+                let mut corners = vec![];
+                for corner in tokens {
+                    // Each corner is v, v/vt, v//vn or v/vt/vn
+                    let mut parts = corner.split('/');
+                    let position =
+                        parser.index(parts.next().unwrap_or(""), mesh.positions.len())?;
+                    let uv = match parts.next() {
+                        Some("") | None => None,
+                        Some(token) => Some(parser.index(token, mesh.uvs.len())?),
+                    };
+                    let normal = match parts.next() {
+                        Some("") | None => None,
+                        Some(token) => Some(parser.index(token, mesh.normals.len())?),
+                    };
+                    corners.push((position, uv, normal));
+                }
+                if corners.len() < 3 {
+                    return Err(parser.error(format!(
+                        "A face needs at least 3 corners but found {}",
+                        corners.len()
+                    )));
+                }
+                // Triangulate the polygon as a fan around its first corner.
+                for k in 1..corners.len() - 1 {
+                    let [a, b, c] = [corners[0], corners[k], corners[k + 1]];
+                    mesh.faces.push(Face {
+                        positions: [a.0, b.0, c.0],
+                        uvs: match (a.1, b.1, c.1) {
+                            (Some(a), Some(b), Some(c)) => Some([a, b, c]),
+                            _ => None,
+                        },
+                        normals: match (a.2, b.2, c.2) {
+                            (Some(a), Some(b), Some(c)) => Some([a, b, c]),
+                            _ => None,
+                        },
+                        material: current_material,
+                    });
+                }
+            }
+            "mtllib" => {
+                for name in tokens {
+                    let mtl_path = path.parent().unwrap_or_else(|| Path::new("")).join(name);
+                    library.extend(load_mtl(&mtl_path)?);
+                }
+            }
+            "usemtl" => {
+                let name = tokens.collect::<Vec<_>>().join(" ");
+                current_material = match material_indices.get(&name) {
+                    Some(&index) => index,
+                    None => {
+                        let material = library.get(&name).ok_or_else(|| {
+                            parser.error(format!("Material '{}' is not defined", name))
+                        })?;
+                        mesh.materials.push(material.to_material()?);
+                        material_indices.insert(name, mesh.materials.len() - 1);
+                        mesh.materials.len() - 1
+                    }
+                };
+            }
+            _ => {}
+        }
+    }
+    Ok(mesh)
+}
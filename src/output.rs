@@ -0,0 +1,186 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+
+use crate::aov::Aov;
+use crate::camera::CameraSettings;
+use crate::image::Image;
+use crate::render::{Frame, Tile};
+
+/// Writing OpenEXR files.
+pub mod exr;
+/// Writing Radiance RGBE files.
+pub mod hdr;
+/// Writing PNG files.
+pub mod png;
+/// Writing PPM files.
+pub mod ppm;
+/// Showing the render in a window.
+#[cfg(feature = "sdl")]
+pub mod window;
+
+/// Somewhere the pixels of a render are sent as they are produced.
+pub trait Output {
+    /// Receive a finished tile of the image, with every sample rendered for it so far. Tiles
+    /// arrive in no particular order.
+    /// Returns false if the render should be stopped early.
+    fn tile(&mut self, _tile: &Tile) -> bool {
+        true
+    }
+
+    /// Called when every tile has been rendered with the given number of samples per pixel. In
+    /// progressive mode this happens after each pass over the image.
+    /// Returns false if the render should be stopped early.
+    fn pass(&mut self, _samples: usize) -> bool {
+        true
+    }
+
+    /// Called regularly while waiting for tiles to be rendered.
+    /// Returns false if the render should be stopped early.
+    fn poll(&mut self) -> bool {
+        true
+    }
+
+    /// Called when rendering has stopped. Returns new camera settings if the scene should be
+    /// rendered again from a different viewpoint, or None if we are done.
+    fn next_camera(&mut self) -> Option<CameraSettings> {
+        None
+    }
+
+    /// Receive the complete image, and any AOVs, once rendering has stopped.
+    fn finish(&mut self, frame: &Frame) -> io::Result<()>;
+}
+
+/// Every output is sent each tile, and the render stops early if any of them ask it to.
+impl Output for Vec<Box<dyn Output>> {
+    fn tile(&mut self, tile: &Tile) -> bool {
+        // Every output must see the tile, so this deliberately does not stop at the first false.
+        let mut keep_going = true;
+        for output in self.iter_mut() {
+            keep_going &= output.tile(tile);
+        }
+        keep_going
+    }
+
+    fn pass(&mut self, samples: usize) -> bool {
+        let mut keep_going = true;
+        for output in self.iter_mut() {
+            keep_going &= output.pass(samples);
+        }
+        keep_going
+    }
+
+    fn poll(&mut self) -> bool {
+        let mut keep_going = true;
+        for output in self.iter_mut() {
+            keep_going &= output.poll();
+        }
+        keep_going
+    }
+
+    /// The first new camera any of the outputs asks for.
+    fn next_camera(&mut self) -> Option<CameraSettings> {
+        self.iter_mut().find_map(|output| output.next_camera())
+    }
+
+    fn finish(&mut self, frame: &Frame) -> io::Result<()> {
+        for output in self.iter_mut() {
+            output.finish(frame)?;
+        }
+        Ok(())
+    }
+}
+
+/// The image file formats we can write.
+#[derive(ValueEnum, Copy, Clone, Debug, PartialEq)]
+pub enum Format {
+    /// 8 bit PNG.
+    Png,
+    /// 8 bit binary PPM.
+    Ppm,
+    /// OpenEXR with 32 bit float channels, holding the linear radiance of each pixel.
+    Exr,
+    /// Radiance RGBE, holding the linear radiance of each pixel.
+    Hdr,
+}
+
+impl Format {
+    /// Choose the format matching the extension of the path, if there is one.
+    pub fn from_path(path: &Path) -> Option<Format> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "png" => Some(Format::Png),
+            "ppm" => Some(Format::Ppm),
+            "exr" => Some(Format::Exr),
+            "hdr" => Some(Format::Hdr),
+            _ => None,
+        }
+    }
+}
+
+/// An Output that writes the finished image to a file.
+///
+/// EXR files hold the AOVs as extra layers. Other formats cannot, so each AOV is written to a
+/// file of its own next to the image, named after it: render.normal.png alongside render.png.
+/// These hold the AOVs mapped to colors that can be viewed, rather than their raw values.
+pub struct FileOutput {
+    path: PathBuf,
+    format: Format,
+}
+
+impl FileOutput {
+    /// Create an output writing to the file at path in the given format.
+    pub fn make(path: &Path, format: Format) -> FileOutput {
+        FileOutput {
+            path: path.to_path_buf(),
+            format,
+        }
+    }
+}
+
+impl FileOutput {
+    /// Write a single image in the output's format.
+    fn write(&self, path: &Path, image: &Image) -> io::Result<()> {
+        match self.format {
+            Format::Png => png::write(path, image),
+            Format::Ppm => ppm::write(path, image),
+            Format::Exr => exr::write(path, image, &[]),
+            Format::Hdr => hdr::write(path, image),
+        }
+    }
+
+    /// The path of the file an AOV is written to, when it isn't part of the image file.
+    fn aov_path(&self, aov: Aov) -> PathBuf {
+        let mut name = self.path.file_stem().unwrap_or_default().to_os_string();
+        name.push(".");
+        name.push(aov.name());
+        if let Some(extension) = self.path.extension() {
+            name.push(".");
+            name.push(extension);
+        }
+        self.path.with_file_name(name)
+    }
+}
+
+impl Output for FileOutput {
+    fn finish(&mut self, frame: &Frame) -> io::Result<()> {
+        if self.format == Format::Exr {
+            return exr::write(&self.path, &frame.image, &frame.aovs);
+        }
+        self.write(&self.path, &frame.image)?;
+        for (aov, image) in &frame.aovs {
+            self.write(&self.aov_path(*aov), &aov.display(image))?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_aovs_are_written_next_to_the_image() {
+    let output = FileOutput::make(Path::new("renders/scene.png"), Format::Png);
+    assert_eq!(
+        PathBuf::from("renders/scene.object_id.png"),
+        output.aov_path(Aov::ObjectId)
+    );
+}
@@ -0,0 +1,130 @@
+use std::io;
+use std::path::Path;
+
+use exr::prelude::{AnyChannel, AnyChannels, FlatSamples, SmallVec, WritableImage};
+
+use crate::aov::Aov;
+use crate::image::Image;
+use crate::vec3::Vec3;
+
+/// Picks out the X, Y and Z, or R, G and B, components of a pixel.
+const COMPONENTS: [fn(Vec3) -> f64; 3] = [|c| c.x, |c| c.y, |c| c.z];
+
+/// Read the R, G and B channels of the first layer of an OpenEXR file as linear colors.
+pub fn read(path: &Path) -> io::Result<Image> {
+    let exr_image = exr::prelude::read_first_rgba_layer_from_file(
+        path,
+        |resolution, _| Image::make(resolution.width(), resolution.height()),
+        |image: &mut Image, position, (r, g, b, _): (f32, f32, f32, f32)| {
+            let color = Vec3 {
+                x: r as f64,
+                y: g as f64,
+                z: b as f64,
+            };
+            image.set(position.x(), position.y(), color);
+        },
+    )
+    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    Ok(exr_image.layer_data.channel_data.pixels)
+}
+
+/// Write the image as an OpenEXR file with 32 bit float R, G and B channels, keeping the linear
+/// radiance of each pixel exactly as rendered. Each AOV is written to the same file as a layer of
+/// channels named after it, such as normal.X or albedo.R, or as a single channel such as depth.
+/// IDs are written as 32 bit unsigned integers.
+pub fn write(path: &Path, image: &Image, aovs: &[(Aov, Image)]) -> io::Result<()> {
+    let mut channels = SmallVec::new();
+    for (name, component) in ["R", "G", "B"].iter().zip(COMPONENTS) {
+        let samples = FlatSamples::F32(samples(image, |c| component(c) as f32));
+        channels.push(AnyChannel::new(*name, samples));
+    }
+    for (aov, aov_image) in aovs {
+        match aov {
+            Aov::MaterialId | Aov::ObjectId => {
+                let samples = FlatSamples::U32(samples(aov_image, |c| c.x as u32));
+                channels.push(AnyChannel::new(aov.name(), samples));
+            }
+            _ if aov.channels().is_empty() => {
+                let samples = FlatSamples::F32(samples(aov_image, |c| c.x as f32));
+                channels.push(AnyChannel::new(aov.name(), samples));
+            }
+            _ => {
+                for (suffix, component) in aov.channels().iter().zip(COMPONENTS) {
+                    let name = format!("{}.{}", aov.name(), suffix);
+                    let samples = FlatSamples::F32(samples(aov_image, |c| component(c) as f32));
+                    channels.push(AnyChannel::new(name.as_str(), samples));
+                }
+            }
+        }
+    }
+
+    exr::prelude::Image::from_channels((image.width, image.height), AnyChannels::sort(channels))
+        .write()
+        .to_file(path)
+        .map_err(|error| match error {
+            exr::error::Error::Io(error) => error,
+            error => io::Error::other(error.to_string()),
+        })
+}
+
+/// The samples of one channel of the image, row by row from the top left.
+fn samples<T>(image: &Image, sample: impl Fn(Vec3) -> T) -> Vec<T> {
+    (0..image.height)
+        .flat_map(|j| (0..image.width).map(move |i| (i, j)))
+        .map(|(i, j)| sample(image.get(i, j)))
+        .collect()
+}
+
+#[test]
+fn test_exr_keeps_radiance_beyond_white() {
+    let mut image = Image::make(3, 2);
+    let bright = Vec3 {
+        x: 12.5,
+        y: 0.25,
+        z: 1e-3,
+    };
+    image.set(2, 1, bright);
+    let path = std::env::temp_dir().join("rays_test_exr_keeps_radiance_beyond_white.exr");
+    write(&path, &image, &[]).unwrap();
+
+    let read = read(&path);
+    std::fs::remove_file(&path).unwrap();
+    let read = read.unwrap();
+    let expected = Vec3 {
+        x: 12.5,
+        y: 0.25,
+        z: 1e-3f32 as f64,
+    };
+    assert_eq!(expected, read.get(2, 1));
+    assert_eq!(Vec3::zero(), read.get(0, 0));
+}
+
+#[test]
+fn test_exr_holds_aovs_as_extra_channels() {
+    let mut ids = Image::make(2, 1);
+    ids.set(1, 0, Vec3::from_one(7.0));
+    let aovs = [(Aov::Normal, Image::make(2, 1)), (Aov::ObjectId, ids)];
+    let path = std::env::temp_dir().join("rays_test_exr_holds_aovs_as_extra_channels.exr");
+    write(&path, &Image::make(2, 1), &aovs).unwrap();
+
+    let read = exr::prelude::read_first_flat_layer_from_file(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let channels = &read.layer_data.channel_data.list;
+    let names: Vec<String> = channels.iter().map(|c| c.name.to_string()).collect();
+    assert_eq!(
+        vec![
+            "B",
+            "G",
+            "R",
+            "normal.X",
+            "normal.Y",
+            "normal.Z",
+            "object_id"
+        ],
+        names
+    );
+    match &channels[6].sample_data {
+        FlatSamples::U32(ids) => assert_eq!(&vec![0, 7], ids),
+        samples => panic!("Expected object IDs to be integers, not {:?}", samples),
+    }
+}
@@ -0,0 +1,250 @@
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::image::Image;
+use crate::vec3::Vec3;
+
+/// Write the image as a Radiance RGBE (.hdr) file, keeping the linear radiance of each pixel to
+/// about 1% precision. Scanlines are written flat, without run length encoding.
+pub fn write(path: &Path, image: &Image) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write!(
+        writer,
+        "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+        image.height, image.width
+    )?;
+    for j in 0..image.height {
+        for i in 0..image.width {
+            writer.write_all(&to_rgbe(image.get(i, j)))?;
+        }
+    }
+    writer.flush()
+}
+
+/// Read a Radiance RGBE (.hdr) file, with scanlines either flat or run length encoded. Only the
+/// usual orientation, with rows from the top and columns from the left, is supported.
+pub fn read(path: &Path) -> io::Result<Image> {
+    let invalid = |message: &str| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} {}", path.display(), message),
+        )
+    };
+    let bytes = fs::read(path)?;
+    let mut lines = bytes.split(|&b| b == b'\n');
+    let mut position = 0;
+    let mut next_line = || {
+        let line = lines.next()?;
+        position += line.len() + 1;
+        Some(String::from_utf8_lossy(line).trim().to_string())
+    };
+
+    // The header is a list of variables ending with a blank line, followed by the resolution.
+    let magic = next_line().unwrap_or_default();
+    if !magic.starts_with("#?") {
+        return Err(invalid("is not a Radiance HDR file"));
+    }
+    loop {
+        match next_line() {
+            None => return Err(invalid("has an incomplete header")),
+            Some(line) if line.is_empty() => break,
+            Some(line) if line.starts_with("FORMAT=") && line != "FORMAT=32-bit_rle_rgbe" => {
+                return Err(invalid("is not in the RGBE format"))
+            }
+            Some(_) => {}
+        }
+    }
+    let resolution = next_line().unwrap_or_default();
+    let (width, height) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+        ["-Y", height, "+X", width] => match (width.parse(), height.parse()) {
+            (Ok(width), Ok(height)) => (width, height),
+            _ => return Err(invalid("has an invalid resolution")),
+        },
+        _ => return Err(invalid("has an unsupported orientation")),
+    };
+    // The resolution comes from the file, so may be too large to count the pixels of. Run length
+    // encoding packs at most 127 values of a component into two bytes, so a file can't hold more
+    // than 16 pixels per byte, and anything claiming to is checked before making the image.
+    let pixels = usize::checked_mul(width, height)
+        .filter(|&pixels| pixels > 0)
+        .ok_or_else(|| invalid("has an invalid resolution"))?;
+    if pixels / 16 > bytes.len().saturating_sub(position) {
+        return Err(invalid("is truncated"));
+    }
+
+    let mut data = bytes.get(position..).unwrap_or_default().iter().copied();
+    let mut image = Image::make(width, height);
+    let mut scanline = vec![[0u8; 4]; width];
+    for j in 0..height {
+        read_scanline(&mut data, &mut scanline).ok_or_else(|| invalid("is truncated"))?;
+        for (i, rgbe) in scanline.iter().enumerate() {
+            image.set(i, j, from_rgbe(*rgbe));
+        }
+    }
+    Ok(image)
+}
+
+/// Read one scanline of RGBE pixels. Run length encoded scanlines start with the bytes 2, 2 and
+/// then the width, and hold each of the four components in turn, as runs of a repeated byte or
+/// of literal bytes. Returns None if the data runs out.
+fn read_scanline(data: &mut impl Iterator<Item = u8>, scanline: &mut [[u8; 4]]) -> Option<()> {
+    let width = scanline.len();
+    let first = [data.next()?, data.next()?, data.next()?, data.next()?];
+    let encoded = (8..0x8000).contains(&width)
+        && first[0] == 2
+        && first[1] == 2
+        && ((first[2] as usize) << 8 | first[3] as usize) == width;
+    if !encoded {
+        scanline[0] = first;
+        for pixel in &mut scanline[1..] {
+            *pixel = [data.next()?, data.next()?, data.next()?, data.next()?];
+        }
+        return Some(());
+    }
+    for component in 0..4 {
+        let mut i = 0;
+        while i < width {
+            let count = data.next()? as usize;
+            if count > 128 {
+                let value = data.next()?;
+                for pixel in scanline.get_mut(i..i + count - 128)? {
+                    pixel[component] = value;
+                }
+                i += count - 128;
+            } else {
+                for pixel in scanline.get_mut(i..i + count)? {
+                    pixel[component] = data.next()?;
+                }
+                // A literal run of no bytes would never end the scanline.
+                i += count.max(1);
+            }
+        }
+    }
+    Some(())
+}
+
+/// Decode a color from three 8 bit mantissas and a shared exponent. The inverse of to_rgbe, to
+/// within its precision.
+pub fn from_rgbe([r, g, b, e]: [u8; 4]) -> Vec3 {
+    if e == 0 {
+        return Vec3::zero();
+    }
+    let scale = 2f64.powi(e as i32 - 128 - 8);
+    Vec3 {
+        x: (r as f64 + 0.5) * scale,
+        y: (g as f64 + 0.5) * scale,
+        z: (b as f64 + 0.5) * scale,
+    }
+}
+
+/// Encode a color as three 8 bit mantissas sharing an 8 bit exponent, that of the brightest
+/// component. Negative and NaN components become zero.
+pub fn to_rgbe(color: Vec3) -> [u8; 4] {
+    let clean = |c: f64| if c > 0.0 { c } else { 0.0 };
+    let (r, g, b) = (clean(color.x), clean(color.y), clean(color.z));
+    let brightest = r.max(g).max(b);
+    if brightest < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+    // Split brightest into mantissa * 2^exponent, with the mantissa in [0.5, 1).
+    let mut exponent = brightest.log2().floor() as i32 + 1;
+    if brightest / 2f64.powi(exponent) >= 1.0 {
+        exponent += 1;
+    }
+    let scale = 256.0 / 2f64.powi(exponent);
+    [
+        (r * scale) as u8,
+        (g * scale) as u8,
+        (b * scale) as u8,
+        (exponent + 128).clamp(0, 255) as u8,
+    ]
+}
+
+#[test]
+fn test_rgbe_keeps_colors_to_within_one_percent_of_the_brightest_component() {
+    for color in [
+        Vec3 {
+            x: 0.25,
+            y: 0.5,
+            z: 1.0,
+        },
+        Vec3 {
+            x: 1234.5,
+            y: 3.0,
+            z: 0.001,
+        },
+        Vec3 {
+            x: 0.003,
+            y: 0.002,
+            z: 0.001,
+        },
+    ] {
+        let decoded = from_rgbe(to_rgbe(color));
+        let brightest = color.x.max(color.y).max(color.z);
+        for axis in 0..3 {
+            assert!((decoded[axis] - color[axis]).abs() <= 0.01 * brightest);
+        }
+    }
+    assert_eq!([0, 0, 0, 0], to_rgbe(Vec3::zero()));
+}
+
+#[test]
+fn test_read_flat_and_run_length_encoded_scanlines() {
+    let path = std::env::temp_dir().join("rays_test_read_flat_and_run_length_encoded.hdr");
+    // Colors between 1 and 2 all share the same exponent.
+    let mut image = Image::make(8, 2);
+    for i in 0..8 {
+        image.set(i, 0, Vec3::from_one(1.0 + 0.1 * i as f64));
+    }
+    write(&path, &image).unwrap();
+    let flat = read(&path).unwrap();
+    let expected: Vec<Vec3> = (0..8)
+        .map(|i| from_rgbe(to_rgbe(image.get(i, 0))))
+        .collect();
+    for (i, &color) in expected.iter().enumerate() {
+        assert_eq!(color, flat.get(i, 0));
+        assert_eq!(Vec3::zero(), flat.get(i, 1));
+    }
+
+    // The same image with both rows run length encoded: the mantissas of the first row as
+    // literal runs, and its exponents and the whole of the black second row as repeated runs.
+    let pixels: Vec<[u8; 4]> = (0..8).map(|i| to_rgbe(image.get(i, 0))).collect();
+    let mut bytes = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 2 +X 8\n".to_vec();
+    bytes.extend([2, 2, 0, 8]);
+    for component in 0..3 {
+        bytes.push(8);
+        bytes.extend(pixels.iter().map(|p| p[component]));
+    }
+    bytes.extend([128 + 8, pixels[0][3]]);
+    bytes.extend([2, 2, 0, 8]);
+    for _ in 0..4 {
+        bytes.extend([128 + 8, 0]);
+    }
+    fs::write(&path, &bytes).unwrap();
+    let encoded = read(&path);
+    fs::remove_file(&path).unwrap();
+    let encoded = encoded.unwrap();
+    for (i, &color) in expected.iter().enumerate() {
+        assert_eq!(color, encoded.get(i, 0));
+        assert_eq!(Vec3::zero(), encoded.get(i, 1));
+    }
+}
+
+#[test]
+fn test_read_rejects_impossible_resolutions() {
+    let path = std::env::temp_dir().join("rays_test_read_rejects_impossible_resolutions.hdr");
+    for resolution in [
+        "-Y 0 +X 8",
+        "-Y 4000000000 +X 4000000000",
+        "-Y 100000 +X 100000",
+    ] {
+        let header = format!("#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n{}\n", resolution);
+        fs::write(&path, [header.as_bytes(), &[0; 64]].concat()).unwrap();
+        let error = read(&path).err().unwrap();
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
+    }
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,17 @@
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+use std::path::Path;
+
+use crate::image::Image;
+
+/// Write the image as an 8 bit RGB PNG file.
+pub fn write(path: &Path, image: &Image) -> io::Result<()> {
+    let writer = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(writer, image.width as u32, image.height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut png_writer = encoder.write_header()?;
+    png_writer.write_image_data(&image.to_rgb8())?;
+    Ok(())
+}
@@ -0,0 +1,14 @@
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::image::Image;
+
+/// Write the image as a binary (P6) PPM file.
+pub fn write(path: &Path, image: &Image) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write!(writer, "P6\n{} {}\n255\n", image.width, image.height)?;
+    writer.write_all(&image.to_rgb8())?;
+    writer.flush()
+}
@@ -0,0 +1,204 @@
+use std::io;
+use std::time::Duration;
+
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::rect::Point;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use sdl2::EventPump;
+
+use crate::camera::CameraSettings;
+use crate::image::vec_to_color;
+use crate::output::Output;
+use crate::render::{Frame, Tile};
+
+/// How far each press of a movement key flies the camera, as a fraction of its distance from
+/// the point it looks at.
+const FLY_STEP: f64 = 0.05;
+/// Radians the camera orbits for each pixel the mouse is dragged.
+const ORBIT_SPEED: f64 = 0.005;
+/// How much each click of the scroll wheel zooms in or out.
+const ZOOM_FACTOR: f64 = 1.1;
+/// How much each press of the aperture keys opens or closes the lens.
+const APERTURE_STEP: f64 = 0.02;
+/// How much each press of the focus keys moves the focus distance.
+const FOCUS_FACTOR: f64 = 1.1;
+
+/// An Output that draws each tile to an SDL window as it is rendered, and shows the number of
+/// samples per pixel finished so far in the window title.
+///
+/// The camera can be moved from the window, which stops the render so it can be started again
+/// from the new viewpoint:
+///
+/// * W, A, S and D fly forwards, left, back and right, and Q and E fly down and up.
+/// * Dragging with the left mouse button orbits around the point the camera looks at.
+/// * The scroll wheel zooms in and out.
+/// * [ and ] close and open the aperture, and , and . move the focus closer and further away.
+/// * R puts the camera back where it started.
+pub struct WindowOutput {
+    canvas: Canvas<Window>,
+    event_pump: EventPump,
+    camera: CameraSettings,
+    initial_camera: CameraSettings,
+    camera_moved: bool,
+    closed: bool,
+}
+
+impl WindowOutput {
+    /// Open a window of the given size, for a render starting from the given camera.
+    pub fn make(width: usize, height: usize, camera: CameraSettings) -> WindowOutput {
+        // Set up SDL to draw to screen
+        let sdl_context = sdl2::init().unwrap();
+        let video_subsystem = sdl_context.video().unwrap();
+
+        let window = video_subsystem
+            .window("RAYS", width as u32, height as u32)
+            .position_centered()
+            .build()
+            .unwrap();
+
+        let mut canvas = window.into_canvas().build().unwrap();
+
+        canvas.set_draw_color(Color::RGBA(255, 0, 255, 255));
+        canvas.clear();
+        canvas.present();
+        let event_pump = sdl_context.event_pump().unwrap();
+        WindowOutput {
+            canvas,
+            event_pump,
+            camera,
+            initial_camera: camera,
+            camera_moved: false,
+            closed: false,
+        }
+    }
+
+    /// Handle any waiting user input. Returns false if the user asked to stop, by closing the
+    /// window or pressing Escape.
+    fn handle_events(&mut self) -> bool {
+        let events: Vec<Event> = self.event_pump.poll_iter().collect();
+        for event in events {
+            match event {
+                Event::Quit { .. } => {
+                    self.closed = true;
+                    return false;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::Escape),
+                    ..
+                } => return false,
+                event => self.control_camera(&event),
+            }
+        }
+        true
+    }
+
+    /// Move the camera if the event is one of the camera controls.
+    fn control_camera(&mut self, event: &Event) {
+        let camera = &self.camera;
+        let step = FLY_STEP * (camera.lookat - camera.lookfrom).magnitude();
+        self.camera = match *event {
+            Event::KeyDown {
+                keycode: Some(keycode),
+                ..
+            } => match keycode {
+                Keycode::W => camera.fly(step, 0.0, 0.0),
+                Keycode::S => camera.fly(-step, 0.0, 0.0),
+                Keycode::D => camera.fly(0.0, step, 0.0),
+                Keycode::A => camera.fly(0.0, -step, 0.0),
+                Keycode::E => camera.fly(0.0, 0.0, step),
+                Keycode::Q => camera.fly(0.0, 0.0, -step),
+                Keycode::RightBracket => CameraSettings {
+                    aperture: camera.aperture + APERTURE_STEP,
+                    ..*camera
+                },
+                Keycode::LeftBracket => CameraSettings {
+                    aperture: (camera.aperture - APERTURE_STEP).max(0.0),
+                    ..*camera
+                },
+                Keycode::Period => CameraSettings {
+                    focus_dist: camera.focus_dist * FOCUS_FACTOR,
+                    ..*camera
+                },
+                Keycode::Comma => CameraSettings {
+                    focus_dist: camera.focus_dist / FOCUS_FACTOR,
+                    ..*camera
+                },
+                Keycode::R => self.initial_camera,
+                _ => return,
+            },
+            Event::MouseMotion {
+                mousestate,
+                xrel,
+                yrel,
+                ..
+            } if mousestate.left() => {
+                camera.orbit(-xrel as f64 * ORBIT_SPEED, yrel as f64 * ORBIT_SPEED)
+            }
+            Event::MouseWheel { y, .. } => camera.zoom(ZOOM_FACTOR.powi(y)),
+            _ => return,
+        };
+        self.camera_moved = true;
+    }
+}
+
+impl Output for WindowOutput {
+    fn tile(&mut self, tile: &Tile) -> bool {
+        for j in 0..tile.height {
+            for i in 0..tile.width {
+                let [r, g, b] = vec_to_color(tile.get(i, j));
+                self.canvas.set_draw_color(Color::RGB(r, g, b));
+                self.canvas
+                    .draw_point(Point::from(((tile.x + i) as i32, (tile.y + j) as i32)))
+                    .unwrap();
+            }
+        }
+
+        // Presenting the canvas after each tile
+        self.canvas.present();
+        self.poll()
+    }
+
+    fn pass(&mut self, samples: usize) -> bool {
+        let title = format!("RAYS - {} samples per pixel", samples);
+        // The title never contains a nul byte, the only way setting it can fail.
+        self.canvas.window_mut().set_title(&title).unwrap();
+        self.canvas.present();
+        self.poll()
+    }
+
+    fn poll(&mut self) -> bool {
+        // Checking regularly prevents windows from saying we are not responding
+        self.handle_events() && !self.camera_moved
+    }
+
+    /// Keep showing the image until the user moves the camera, or closes the window or presses
+    /// Escape.
+    fn next_camera(&mut self) -> Option<CameraSettings> {
+        while !self.camera_moved {
+            if self.closed {
+                return None;
+            }
+            self.canvas.present();
+            if !self.handle_events() {
+                self.closed = true;
+                return None;
+            }
+            ::std::thread::sleep(Duration::new(0, 1_000_000_000u32 / 60));
+        }
+        self.camera_moved = false;
+        Some(self.camera)
+    }
+
+    /// Keep the window open showing the image until the user closes it.
+    fn finish(&mut self, _frame: &Frame) -> io::Result<()> {
+        while !self.closed {
+            self.canvas.present();
+            self.closed = !self.handle_events();
+            ::std::thread::sleep(Duration::new(0, 1_000_000_000u32 / 60));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,53 @@
+use crate::aabb::Aabb;
+use crate::hit::{HitOrMiss, Hittable};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// A flat surface stretching forever in every direction, such as the ground.
+///
+/// Planes have no bounding box, so every ray is tested against them rather than them being
+/// sorted into a BVH.
+pub struct Plane {
+    point: Vec3,
+    normal: Vec3,
+    /// Axes along the plane, for texture coordinates.
+    axes: (Vec3, Vec3),
+    material: Box<dyn Material>,
+}
+
+impl Plane {
+    /// Create a plane through point, facing out along normal. Texture coordinates are distances
+    /// from point along two directions in the plane, so image textures repeat every unit.
+    pub fn make(point: Vec3, normal: Vec3, material: Box<dyn Material>) -> Plane {
+        let normal = normal.normalize();
+        Plane {
+            point,
+            normal,
+            axes: normal.perpendiculars(),
+            material,
+        }
+    }
+}
+
+impl Hittable for Plane {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> HitOrMiss<'_> {
+        let denominator = self.normal.dot(&ray.direction);
+        // The ray runs along the plane.
+        if denominator.abs() < 1e-12 {
+            return HitOrMiss::Miss;
+        }
+        let t = self.normal.dot(&(self.point - ray.origin)) / denominator;
+        if t < t_min || t_max < t {
+            return HitOrMiss::Miss;
+        }
+        let p = ray.at(t);
+        let offset = p - self.point;
+        let uv = (offset.dot(&self.axes.0), offset.dot(&self.axes.1));
+        HitOrMiss::hit(p, self.normal, t, uv, ray, &*self.material)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        None
+    }
+}
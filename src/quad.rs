@@ -0,0 +1,154 @@
+use crate::aabb::Aabb;
+use crate::hit::{HitOrMiss, Hittable};
+use crate::light::{Light, QuadLight};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// A flat four sided shape: a parallelogram with one corner at corner and sides along u and v.
+/// With u and v at right angles it is a rectangle, and with them along two axes, an
+/// axis-aligned rectangle.
+pub struct Quad {
+    corner: Vec3,
+    u: Vec3,
+    v: Vec3,
+    /// The unit normal, along u x v.
+    normal: Vec3,
+    /// Turns a point in the plane of the quad into its coordinates along u and v.
+    w: Vec3,
+    material: Box<dyn Material>,
+}
+
+impl Quad {
+    /// Create a quad with corners at corner, corner + u, corner + u + v and corner + v. Its
+    /// outside faces along u x v, so the corners go anticlockwise seen from outside. Texture
+    /// coordinates run from 0 to 1 along u and v.
+    pub fn make(corner: Vec3, u: Vec3, v: Vec3, material: Box<dyn Material>) -> Quad {
+        let n = u.cross(&v);
+        Quad {
+            corner,
+            u,
+            v,
+            normal: n.normalize(),
+            w: n / n.norm(),
+            material,
+        }
+    }
+}
+
+impl Hittable for Quad {
+    /// Hit the plane of the quad, then check the hit is within its sides.
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> HitOrMiss<'_> {
+        let denominator = self.normal.dot(&ray.direction);
+        // The ray runs along the plane of the quad.
+        if denominator.abs() < 1e-12 {
+            return HitOrMiss::Miss;
+        }
+        let t = self.normal.dot(&(self.corner - ray.origin)) / denominator;
+        if t < t_min || t_max < t {
+            return HitOrMiss::Miss;
+        }
+        let p = ray.at(t);
+        let offset = p - self.corner;
+        let alpha = self.w.dot(&offset.cross(&self.v));
+        let beta = self.w.dot(&self.u.cross(&offset));
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return HitOrMiss::Miss;
+        }
+        HitOrMiss::hit(p, self.normal, t, (alpha, beta), ray, &*self.material)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let c = self.corner;
+        // Pad the box so that axis-aligned quads do not have a box with no thickness.
+        Some(
+            Aabb { min: c, max: c }
+                .expand(c + self.u)
+                .expand(c + self.v)
+                .expand(c + self.u + self.v)
+                .pad(1e-4),
+        )
+    }
+
+    fn lights(&self) -> Vec<Box<dyn Light>> {
+        if self.material.emits_light() {
+            vec![Box::from(QuadLight {
+                corner: self.corner,
+                u: self.u,
+                v: self.v,
+            })]
+        } else {
+            vec![]
+        }
+    }
+}
+
+#[test]
+fn test_ray_hits_quad_inside_sides_only() {
+    use crate::material::lambertian::Lambertian;
+
+    // A rectangle in the z = -1 plane from (0, 0) to (2, 1), facing the origin.
+    let quad = Quad::make(
+        Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: -1.0,
+        },
+        Vec3 {
+            x: 2.0,
+            y: 0.0,
+            z: 0.0,
+        },
+        Vec3 {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        },
+        Box::from(Lambertian::make(Vec3::from_one(0.5))),
+    );
+    let towards = |x: f64, y: f64| Ray {
+        origin: Vec3::zero(),
+        direction: Vec3 { x, y, z: -1.0 },
+        time: 0.0,
+    };
+
+    match quad.hit(&towards(1.5, 0.25), 0.001, f64::INFINITY) {
+        HitOrMiss::Hit { t, point, .. } => {
+            assert!((t - 1.0).abs() < 1e-12);
+            assert!((point.u - 0.75).abs() < 1e-12 && (point.v - 0.25).abs() < 1e-12);
+            assert!(point.front_face);
+            assert_eq!(1.0, point.normal.z);
+        }
+        HitOrMiss::Miss => panic!("Expected the ray to hit the quad"),
+    }
+    assert!(matches!(
+        quad.hit(&towards(2.5, 0.5), 0.001, f64::INFINITY),
+        HitOrMiss::Miss
+    ));
+    assert!(matches!(
+        quad.hit(&towards(1.0, -0.1), 0.001, f64::INFINITY),
+        HitOrMiss::Miss
+    ));
+
+    // From behind, the ray hits the back face and the normal still faces it.
+    let behind = Ray {
+        origin: Vec3 {
+            x: 1.0,
+            y: 0.5,
+            z: -3.0,
+        },
+        direction: Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: 1.0,
+        },
+        time: 0.0,
+    };
+    match quad.hit(&behind, 0.001, f64::INFINITY) {
+        HitOrMiss::Hit { point, .. } => {
+            assert!(!point.front_face);
+            assert_eq!(-1.0, point.normal.z);
+        }
+        HitOrMiss::Miss => panic!("Expected the ray to hit the back of the quad"),
+    }
+}
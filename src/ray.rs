@@ -2,8 +2,13 @@ use crate::vec3::Vec3;
 
 /// A Ray that is shot into the scene. The Ray has a starting position ("origin") and a direction.
 pub struct Ray {
+    /// Where the ray starts.
     pub origin: Vec3,
+    /// The direction the ray travels in. Not necessarily of length 1.
     pub direction: Vec3,
+    /// The moment the ray was sent, while the camera's shutter was open. Moving objects are hit
+    /// where they were at that moment.
+    pub time: f64,
 }
 
 impl Ray {
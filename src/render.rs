@@ -0,0 +1,469 @@
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use crate::aov::{Aov, AovPixel};
+use crate::camera::Camera;
+use crate::environment::Environment;
+use crate::hit::{HitOrMiss, Hittable};
+use crate::image::Image;
+use crate::integrator::{Integrator, World};
+use crate::output::Output;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::vec3::Vec3;
+
+/// The width and height in pixels of the square tiles the image is split into.
+const TILE_SIZE: usize = 32;
+
+/// How often we give the output a chance to handle events while waiting for tiles.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Settings controlling the size and quality of a render.
+pub struct Settings {
+    /// The width of the image in pixels.
+    pub width: usize,
+    /// The height of the image in pixels.
+    pub height: usize,
+    /// The number of rays traced through each pixel, which are averaged to give its color.
+    pub samples_per_pixel: usize,
+    /// How the light arriving along each ray from the camera is worked out.
+    pub integrator: Box<dyn Integrator>,
+    /// The number of worker threads to render with.
+    pub threads: usize,
+    /// The light arriving from rays which hit nothing.
+    pub environment: Environment,
+    /// Seeds the random numbers used for each pixel. Rendering with the same seed and settings
+    /// always gives the same image, however many threads are used.
+    pub seed: u64,
+    /// Render the whole image one sample per pixel at a time, accumulating the passes, rather
+    /// than finishing each pixel before moving on.
+    pub progressive: bool,
+    /// The AOVs to render alongside the image. Rendering them costs an extra intersection test
+    /// per sample, so none are rendered unless asked for.
+    pub aovs: Vec<Aov>,
+}
+
+/// A finished render: the image, and the AOVs that were asked for.
+pub struct Frame {
+    /// The rendered image.
+    pub image: Image,
+    /// Each AOV that was asked for, with its raw values. Single values, like depth, are repeated
+    /// in all three components of each pixel.
+    pub aovs: Vec<(Aov, Image)>,
+}
+
+/// A rectangular section of the image, along with the colors of its pixels once rendered.
+pub struct Tile {
+    /// The column of the left edge of the tile in the image.
+    pub x: usize,
+    /// The row of the top edge of the tile in the image.
+    pub y: usize,
+    /// The width of the tile in pixels.
+    pub width: usize,
+    /// The height of the tile in pixels.
+    pub height: usize,
+    /// The number of samples taken for every pixel of the tile.
+    pub samples: usize,
+    /// The sum of the samples for each pixel of the tile, row by row from the top left.
+    pub pixels: Vec<Vec3>,
+    /// What the samples of each pixel first hit, summed, in the same order. Empty unless AOVs
+    /// are being rendered.
+    pub aovs: Vec<AovPixel>,
+}
+
+impl Tile {
+    /// The color of pixel (i, j) of the tile, averaged over its samples.
+    pub fn get(&self, i: usize, j: usize) -> Vec3 {
+        self.pixels[j * self.width + i] / self.samples.max(1) as f64
+    }
+}
+
+/// The accumulation buffer. Holds the sum of every sample taken so far for each pixel of the
+/// image, so that passes can keep adding to it and it can be averaged into an Image at any time.
+struct Film {
+    width: usize,
+    sums: Vec<Vec3>,
+    samples: Vec<usize>,
+    aovs: Vec<AovPixel>,
+}
+
+impl Film {
+    fn make(width: usize, height: usize, aovs: bool) -> Film {
+        Film {
+            width,
+            sums: vec![Vec3::zero(); width * height],
+            samples: vec![0; width * height],
+            aovs: if aovs {
+                vec![AovPixel::zero(); width * height]
+            } else {
+                vec![]
+            },
+        }
+    }
+
+    /// Add the samples of a rendered tile.
+    fn add(&mut self, tile: &Tile) {
+        for j in 0..tile.height {
+            for i in 0..tile.width {
+                let index = (tile.y + j) * self.width + tile.x + i;
+                self.sums[index] = self.sums[index] + tile.pixels[j * tile.width + i];
+                if let Some(aov) = tile.aovs.get(j * tile.width + i) {
+                    self.aovs[index].add(aov, self.samples[index] == 0);
+                }
+                self.samples[index] += tile.samples;
+            }
+        }
+    }
+
+    /// A copy of the tile with everything accumulated for its pixels so far.
+    fn accumulated(&self, tile: &Tile) -> Tile {
+        let mut pixels = Vec::with_capacity(tile.width * tile.height);
+        let mut aovs = vec![];
+        for j in tile.y..tile.y + tile.height {
+            pixels.extend_from_slice(&self.sums[j * self.width + tile.x..][..tile.width]);
+            if !self.aovs.is_empty() {
+                aovs.extend_from_slice(&self.aovs[j * self.width + tile.x..][..tile.width]);
+            }
+        }
+        Tile {
+            // Tiles are the same in every pass, so all of a tile's pixels have the same count.
+            samples: self.samples[tile.y * self.width + tile.x],
+            pixels,
+            aovs,
+            ..*tile
+        }
+    }
+
+    /// The image so far, along with the given AOVs. Pixels without any samples are black.
+    fn frame(&self, aovs: &[Aov]) -> Frame {
+        let height = self.sums.len() / self.width;
+        let mut image = Image::make(self.width, height);
+        for (index, (&sum, &samples)) in self.sums.iter().zip(&self.samples).enumerate() {
+            if samples > 0 {
+                image.set(index % self.width, index / self.width, sum / samples as f64);
+            }
+        }
+        let aovs = aovs
+            .iter()
+            .map(|&aov| {
+                let mut aov_image = Image::make(self.width, height);
+                for (index, pixel) in self.aovs.iter().enumerate() {
+                    let value = aov.value(pixel, self.sums[index], self.samples[index]);
+                    aov_image.set(index % self.width, index / self.width, value);
+                }
+                (aov, aov_image)
+            })
+            .collect();
+        Frame { image, aovs }
+    }
+}
+
+/// What the ray hits first, for the AOVs. Everything is zero if it hits nothing, apart from the
+/// albedo, which is the background.
+fn first_hit(ray: &Ray, world: &World) -> AovPixel {
+    match world.hittable.hit(ray, 0.001, f64::INFINITY) {
+        HitOrMiss::Hit {
+            t,
+            point,
+            material,
+            object_id,
+            material_id,
+        } => AovPixel {
+            normal: point.normal,
+            albedo: material.albedo(&point),
+            position: point.p,
+            depth: t * ray.direction.magnitude(),
+            color_squared: Vec3::zero(),
+            object_id,
+            material_id,
+        },
+        HitOrMiss::Miss => AovPixel {
+            albedo: world.environment.color(ray),
+            ..AovPixel::zero()
+        },
+    }
+}
+
+/// Split an image of the given size into tiles, in the order they should be rendered.
+fn tiles(width: usize, height: usize) -> Vec<Tile> {
+    let mut tiles = vec![];
+    for y in (0..height).step_by(TILE_SIZE) {
+        for x in (0..width).step_by(TILE_SIZE) {
+            tiles.push(Tile {
+                x,
+                y,
+                width: TILE_SIZE.min(width - x),
+                height: TILE_SIZE.min(height - y),
+                samples: 0,
+                pixels: vec![],
+                aovs: vec![],
+            });
+        }
+    }
+    tiles
+}
+
+/// Trace the given range of samples for every pixel in the tile. Each sample has its own
+/// Sampler, seeded from its pixel and number, so it is rendered the same no matter which thread
+/// renders it or in which pass.
+fn render_tile(
+    tile: &mut Tile,
+    samples: Range<usize>,
+    camera: &Camera,
+    world: &World,
+    settings: &Settings,
+) {
+    let (width, height) = (settings.width, settings.height);
+    tile.samples = samples.len();
+    tile.pixels = Vec::with_capacity(tile.width * tile.height);
+    tile.aovs = vec![];
+    for j in tile.y..tile.y + tile.height {
+        for i in tile.x..tile.x + tile.width {
+            let mut pixel_color = Vec3::zero();
+            let mut pixel_aovs = AovPixel::zero();
+            for sample in samples.clone() {
+                let mut sampler = Sampler::for_sample(settings.seed, i, j, sample);
+                // Pixel (i, j) covers u from i / width to (i + 1) / width, and rows run down from
+                // the top of the image at v = 1.
+                let u = (i as f64 + sampler.random_double()) / width as f64;
+                let v = ((height - 1 - j) as f64 + sampler.random_double()) / height as f64;
+                let ray = camera.get_ray(u, v, &mut sampler);
+
+                let color = settings.integrator.radiance(&ray, world, &mut sampler);
+                pixel_color = pixel_color + color;
+                if !settings.aovs.is_empty() {
+                    let aovs = AovPixel {
+                        color_squared: color * color,
+                        ..first_hit(&ray, world)
+                    };
+                    pixel_aovs.add(&aovs, sample == samples.start);
+                }
+            }
+            tile.pixels.push(pixel_color);
+            if !settings.aovs.is_empty() {
+                tile.aovs.push(pixel_aovs);
+            }
+        }
+    }
+}
+
+/// Render the given range of samples for every tile, splitting the work between
+/// settings.threads worker threads. Each tile is added to the film and sent to the output, with
+/// everything accumulated for it so far, as it is finished. Returns false if the output asked for
+/// the render to stop.
+fn render_pass(
+    tiles: &[Tile],
+    samples: Range<usize>,
+    camera: &Camera,
+    world: &World,
+    settings: &Settings,
+    film: &mut Film,
+    output: &mut dyn Output,
+) -> bool {
+    // Workers claim the next unrendered tile by incrementing this counter.
+    let next_tile = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let (sender, receiver) = mpsc::channel();
+
+    thread::scope(|scope| {
+        for _ in 0..settings.threads.max(1) {
+            let sender = sender.clone();
+            let (next_tile, stop, samples) = (&next_tile, &stop, samples.clone());
+            scope.spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    let index = next_tile.fetch_add(1, Ordering::Relaxed);
+                    let tile = match tiles.get(index) {
+                        Some(tile) => tile,
+                        None => break,
+                    };
+                    let mut rendered = Tile {
+                        pixels: vec![],
+                        aovs: vec![],
+                        ..*tile
+                    };
+                    render_tile(&mut rendered, samples.clone(), camera, world, settings);
+                    if sender.send(rendered).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        // Only the workers hold senders now, so the channel disconnects once they are all done.
+        drop(sender);
+
+        loop {
+            let keep_going = match receiver.recv_timeout(POLL_INTERVAL) {
+                Ok(tile) => {
+                    film.add(&tile);
+                    output.tile(&film.accumulated(&tile))
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => output.poll(),
+                Err(mpsc::RecvTimeoutError::Disconnected) => return true,
+            };
+            if !keep_going {
+                stop.store(true, Ordering::Relaxed);
+                return false;
+            }
+        }
+    })
+}
+
+/// Render the world as seen by the camera into an Image, along with any AOVs asked for in the
+/// settings, splitting the work into tiles shared between settings.threads worker threads. Each
+/// tile is sent to the output as it is finished.
+///
+/// The light arriving along each ray from the camera is worked out by settings.integrator.
+///
+/// Normally every sample of a tile is taken before moving on to the next tile. In progressive
+/// mode the whole image is rendered with one sample per pixel, then again and again, adding to
+/// what came before, and the output is told as each pass completes. Both give the same image.
+///
+/// Stops early if the output asks it to. The frame then holds everything rendered so far, with
+/// the remaining pixels black.
+pub fn render(
+    camera: &Camera,
+    world: &dyn Hittable,
+    settings: &Settings,
+    output: &mut dyn Output,
+) -> Frame {
+    let world = World::make(world, &settings.environment);
+    let tiles = tiles(settings.width, settings.height);
+    let mut film = Film::make(settings.width, settings.height, !settings.aovs.is_empty());
+    let samples_per_pass = if settings.progressive {
+        1
+    } else {
+        settings.samples_per_pixel
+    };
+
+    let mut samples = 0;
+    while samples < settings.samples_per_pixel {
+        let pass = samples..samples + samples_per_pass;
+        if !render_pass(&tiles, pass, camera, &world, settings, &mut film, output) {
+            break;
+        }
+        samples += samples_per_pass;
+        if !output.pass(samples) {
+            break;
+        }
+    }
+    film.frame(&settings.aovs)
+}
+
+/// Somewhere for the tests to send renders, which ignores them.
+#[cfg(test)]
+struct Discard;
+
+#[cfg(test)]
+impl Output for Discard {
+    fn finish(&mut self, _frame: &Frame) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A grey sphere of radius 1 at the origin, labelled as object 1, and a camera looking at it
+/// from 5 units away along the z axis.
+#[cfg(test)]
+fn test_scene(aspect_ratio: f64) -> (crate::hit::HittableList, Camera) {
+    use crate::camera::CameraSettings;
+    use crate::hit::{HittableList, Labelled};
+    use crate::material::lambertian::Lambertian;
+    use crate::sphere::Sphere;
+
+    let world = HittableList {
+        hittables: vec![Box::from(Labelled {
+            hittable: Box::from(Sphere {
+                center: Vec3::zero(),
+                r: 1.0,
+                material: Box::from(Lambertian::make(Vec3::from_one(0.5))),
+            }),
+            object_id: 1,
+            material_id: 1,
+        })],
+    };
+    let camera = CameraSettings {
+        lookfrom: Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: 5.0,
+        },
+        lookat: Vec3::zero(),
+        vup: Vec3 {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        },
+        vfov: 40.0,
+        aperture: 0.1,
+        focus_dist: 5.0,
+        shutter_open: 0.0,
+        shutter_close: 0.0,
+    }
+    .camera(aspect_ratio);
+    (world, camera)
+}
+
+#[test]
+fn test_progressive_render_matches_render_tile_by_tile() {
+    use crate::integrator::{PathTracer, ROULETTE_DEPTH};
+
+    let (world, camera) = test_scene(1.5);
+    let settings = |progressive| Settings {
+        width: 48,
+        height: 32,
+        samples_per_pixel: 4,
+        integrator: Box::from(PathTracer::make(10, ROULETTE_DEPTH)),
+        threads: 3,
+        environment: Environment::sky(),
+        seed: 1,
+        progressive,
+        aovs: vec![Aov::Normal, Aov::ObjectId, Aov::Variance],
+    };
+
+    let tile_by_tile = render(&camera, &world, &settings(false), &mut Discard);
+    let progressive = render(&camera, &world, &settings(true), &mut Discard);
+    for j in 0..32 {
+        for i in 0..48 {
+            assert_eq!(tile_by_tile.image.get(i, j), progressive.image.get(i, j));
+            for ((_, a), (_, b)) in tile_by_tile.aovs.iter().zip(&progressive.aovs) {
+                assert_eq!(a.get(i, j), b.get(i, j));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_aovs_describe_the_first_hit() {
+    use crate::integrator::{PathTracer, ROULETTE_DEPTH};
+
+    let (world, camera) = test_scene(1.0);
+    let settings = Settings {
+        width: 33,
+        height: 33,
+        samples_per_pixel: 2,
+        integrator: Box::from(PathTracer::make(10, ROULETTE_DEPTH)),
+        threads: 2,
+        environment: Environment::Solid(Vec3::from_one(0.25)),
+        seed: 1,
+        progressive: false,
+        aovs: vec![Aov::Normal, Aov::Albedo, Aov::Depth, Aov::ObjectId],
+    };
+
+    let frame = render(&camera, &world, &settings, &mut Discard);
+    let aov = |aov: Aov, i, j| {
+        let (_, image) = frame.aovs.iter().find(|(a, _)| *a == aov).unwrap();
+        image.get(i, j)
+    };
+    // The middle pixel sees the front of the sphere, facing the camera about 4 units away.
+    let normal = aov(Aov::Normal, 16, 16);
+    assert!(normal.z > 0.95 && (normal.magnitude() - 1.0).abs() < 0.01);
+    assert!((aov(Aov::Depth, 16, 16).x - 4.0).abs() < 0.1);
+    assert_eq!(aov(Aov::Albedo, 16, 16), Vec3::from_one(0.5));
+    assert_eq!(aov(Aov::ObjectId, 16, 16), Vec3::from_one(1.0));
+    // The corners see only the background.
+    assert_eq!(aov(Aov::Albedo, 0, 0), Vec3::from_one(0.25));
+    assert_eq!(aov(Aov::Depth, 0, 0), Vec3::zero());
+    assert_eq!(aov(Aov::ObjectId, 0, 0), Vec3::zero());
+}
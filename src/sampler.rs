@@ -0,0 +1,74 @@
+/// A source of random numbers for rendering.
+///
+/// Every sample of every pixel gets its own Sampler, seeded from the render's seed, the pixel's
+/// coordinates and the number of the sample. This means the random numbers used for a sample, and
+/// so its color, depend only on the seed and which sample it is, and not on which thread rendered
+/// it or in what order.
+///
+/// Uses the SplitMix64 generator, which is fast, has a tiny state, and produces the same
+/// sequence on every platform.
+pub struct Sampler {
+    state: u64,
+}
+
+const GOLDEN_GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// The SplitMix64 output function. Scrambles the bits of z so that similar inputs give very
+/// different outputs.
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+impl Sampler {
+    /// Create a sampler from a single seed.
+    pub fn make(seed: u64) -> Sampler {
+        Sampler { state: mix(seed) }
+    }
+
+    /// Create the sampler for the given sample of pixel (i, j) of a render with the given seed.
+    pub fn for_sample(seed: u64, i: usize, j: usize, sample: usize) -> Sampler {
+        let pixel = mix(mix(seed ^ i as u64) ^ (j as u64).wrapping_mul(GOLDEN_GAMMA));
+        Sampler::make(pixel ^ (sample as u64).wrapping_mul(GOLDEN_GAMMA))
+    }
+
+    /// The next 64 random bits.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(GOLDEN_GAMMA);
+        mix(self.state)
+    }
+
+    /// Returns a random number in [0,1)
+    pub fn random_double(&mut self) -> f64 {
+        // The top 53 bits fill the mantissa of an f64 exactly.
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+
+    /// Returns a random number in [min,max)
+    pub fn random_range(&mut self, min: f64, max: f64) -> f64 {
+        min + (max - min) * self.random_double()
+    }
+}
+
+#[test]
+fn test_sample_samplers_are_repeatable_and_independent() {
+    let sequence = |mut sampler: Sampler| (0..8).map(|_| sampler.next_u64()).collect::<Vec<_>>();
+
+    assert_eq!(
+        sequence(Sampler::for_sample(42, 3, 7, 0)),
+        sequence(Sampler::for_sample(42, 3, 7, 0))
+    );
+    assert_ne!(
+        sequence(Sampler::for_sample(42, 3, 7, 0)),
+        sequence(Sampler::for_sample(42, 7, 3, 0))
+    );
+    assert_ne!(
+        sequence(Sampler::for_sample(42, 3, 7, 0)),
+        sequence(Sampler::for_sample(43, 3, 7, 0))
+    );
+    assert_ne!(
+        sequence(Sampler::for_sample(42, 3, 7, 0)),
+        sequence(Sampler::for_sample(42, 3, 7, 1))
+    );
+}
@@ -0,0 +1,1315 @@
+//! Loading of scenes from TOML files.
+//!
+//! A scene file describes the image to render, the camera, a set of named materials and the
+//! objects in the world. For example:
+//!
+//! ```toml
+//! [image]
+//! width = 400
+//! height = 300
+//! samples_per_pixel = 50
+//! max_depth = 50
+//!
+//! [camera]
+//! lookfrom = [13.0, 2.0, 3.0]
+//! lookat = [0.0, 0.0, 0.0]
+//! vfov = 20.0
+//! aperture = 0.1
+//! focus_dist = 10.0
+//!
+//! [environment]
+//! type = "gradient"
+//! bottom = [1.0, 1.0, 1.0]
+//! top = [0.5, 0.7, 1.0]
+//!
+//! [materials.ground]
+//! type = "uniform_scatterer"
+//! albedo = [0.5, 0.5, 0.5]
+//!
+//! [[objects]]
+//! type = "plane"
+//! point = [0.0, 0.0, 0.0]
+//! normal = [0.0, 1.0, 0.0]
+//! material = "ground"
+//!
+//! [[objects]]
+//! type = "mesh"
+//! path = "teapot.obj"
+//! ```
+//!
+//! The environment, the light from rays that hit nothing, may also be `type = "solid"` with a
+//! `color`, or `type = "map"` with the `path` of an equirectangular `.hdr` or `.exr` image, an
+//! optional `rotation` in degrees about the y axis and an optional `intensity` to scale it by. It
+//! defaults to the gradient above, and may also be written `[background]` as in older scenes.
+//!
+//! Besides spheres and meshes, objects may be infinite planes through a `point` facing along a
+//! `normal`, `quad`s with one `corner` and sides along `u` and `v`, facing along u x v, `disk`s
+//! with a `center`, `normal` and `radius`, and axis-aligned `box`es between corners `min` and
+//! `max`.
+//!
+//! Any object may be moved into place with a `transform`, a list of steps applied in order:
+//!
+//! ```toml
+//! transform = [
+//!     { scale = [1.0, 2.0, 1.0] },
+//!     { rotate_y = 30.0 },
+//!     { rotate = { axis = [1.0, 1.0, 0.0], angle = 45.0 } },
+//!     { translate = [0.0, 1.0, 0.0] },
+//! ]
+//! ```
+//!
+//! Angles are in degrees, and `rotate_x` and `rotate_z` turn about the other axes.
+//!
+//! Objects can move while the camera's shutter is open, from `shutter_open` to `shutter_close`
+//! in `[camera]`, which default to 0 for a still image. A sphere with a `center_end` moves from
+//! `center` at time 0 to there at time 1, and any object with a `transform_end` moves from its
+//! `transform` to that, which must have the same steps in the same order with different amounts.
+//! Objects stay put before time 0 and after time 1.
+//!
+//! An object other than a mesh with a `density` isn't drawn, but filled with fog or smoke of that
+//! density instead, scattering light by its material, which should be a phase function. The
+//! object's `transform` moves the fog with it.
+//!
+//! A `box` with a density may also have a `grid`, a voxel grid filling the box whose values
+//! scale the density from place to place, for clouds and smoke:
+//!
+//! ```toml
+//! grid = { path = "plume.vol", emit = [40.0, 12.0, 2.0], emission = "flame.vol" }
+//! ```
+//!
+//! Grids are Mitsuba `.vol` files of floats or bytes, or headerless `.raw` files of one byte per
+//! voxel, which need their `resolution`. With an `emit` color the medium glows like fire, giving
+//! off that color times the values of the `emission` grid, or of the density grid if none is
+//! given. Grids can't move.
+//!
+//! Mesh and map paths are relative to the scene file, and meshes take their materials from their
+//! own MTL libraries. A mesh used by several objects is only loaded once, and its geometry shared
+//! between them.
+//!
+//! Material types are `lambertian`, `uniform_scatterer` and `metal` with an `albedo`,
+//! `dielectric` with an `index_of_refraction`, `diffuse_light` with an `emit` color, and
+//! `conductor`, a physically based metal given either as a `metal` (`gold`, `silver`, `copper` or
+//! `aluminium`) or by its complex index of refraction `eta` and `k`. Conductors and dielectrics
+//! take an optional `roughness` from 0 to 1. The phase functions for fog are `isotropic` with an
+//! `albedo`, and `henyey_greenstein` with an `albedo` and `g` between -1 and 1, scattering light
+//! forwards when positive and backwards when negative.
+//!
+//! For the object ID and material ID AOVs, objects are numbered from 1 in the order they are
+//! listed, and materials from 1 in order of name, followed by the materials of each mesh file in turn.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::de::value::{MapAccessDeserializer, SeqAccessDeserializer};
+use serde::de::{MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use toml::Spanned;
+
+use crate::bvh::BvhNode;
+use crate::camera::CameraSettings;
+use crate::cuboid::Cuboid;
+use crate::disk::Disk;
+use crate::environment::{Environment, EnvironmentMap};
+use crate::hit::{Hittable, HittableList, Labelled};
+use crate::image::Image;
+use crate::instance::Instance;
+use crate::material::conductor::{Conductor, MetalKind};
+use crate::material::dielectric::Dielectric;
+use crate::material::diffuse_light::DiffuseLight;
+use crate::material::henyey_greenstein::HenyeyGreenstein;
+use crate::material::isotropic::Isotropic;
+use crate::material::lambertian::Lambertian;
+use crate::material::metal::Metal;
+use crate::material::uniform_scatterer::UniformScatterer;
+use crate::material::Material;
+use crate::medium::{ConstantMedium, GridMedium, Medium, Volume};
+use crate::moving_sphere::MovingSphere;
+use crate::obj;
+use crate::obj::ObjError;
+use crate::plane::Plane;
+use crate::quad::Quad;
+use crate::sampler::Sampler;
+use crate::sphere::Sphere;
+use crate::texture::checker::Checker;
+use crate::texture::image_texture::ImageTexture;
+use crate::texture::noise::{NoiseKind, NoiseTexture};
+use crate::texture::solid_color::SolidColor;
+use crate::texture::Texture;
+use crate::transform::{Motion, Transform, TransformStep};
+use crate::vec3::Vec3;
+use crate::voxel::VoxelGrid;
+
+// The image settings for the default scene
+const WIDTH: usize = 1200;
+const HEIGHT: usize = (WIDTH as f64 / ASPECT_RATIO) as usize;
+const ASPECT_RATIO: f64 = 3.0 / 2.0;
+const SAMPLES_PER_PIXEL: usize = 50;
+const MAX_DEPTH: usize = 50;
+
+/// Everything needed to render an image: what the world looks like, where we are looking at it
+/// from, and how big and how good the picture should be.
+pub struct Scene {
+    /// Where the world is seen from.
+    pub camera: CameraSettings,
+    /// Every object in the world.
+    pub world: HittableList,
+    /// The light arriving from rays which hit nothing.
+    pub environment: Environment,
+    /// The width of the image in pixels.
+    pub width: usize,
+    /// The height of the image in pixels.
+    pub height: usize,
+    /// The number of rays traced through each pixel.
+    pub samples_per_pixel: usize,
+    /// The most times a ray may bounce before it is considered absorbed.
+    pub max_depth: usize,
+}
+
+/// The reasons loading a scene file can fail.
+#[derive(Debug)]
+pub enum SceneError {
+    /// A file could not be read.
+    Io {
+        /// The file that could not be read.
+        path: PathBuf,
+        /// Why it could not be read.
+        error: io::Error,
+    },
+    /// The file is not valid TOML, or does not match the expected layout.
+    Parse {
+        /// The scene file.
+        path: PathBuf,
+        /// What was wrong with it, and where.
+        error: toml::de::Error,
+    },
+    /// The file parsed, but describes something that does not make sense.
+    Invalid {
+        /// The scene file.
+        path: PathBuf,
+        /// The line of the scene file the problem is on.
+        line: usize,
+        /// What the problem is.
+        message: String,
+    },
+    /// The scene has no objects in it.
+    Empty {
+        /// The scene file.
+        path: PathBuf,
+    },
+    /// A mesh used by the scene could not be loaded.
+    Mesh(ObjError),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            SceneError::Parse { path, error } => write!(f, "{}: {}", path.display(), error),
+            SceneError::Invalid {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", path.display(), line, message),
+            SceneError::Empty { path } => write!(f, "{}: The scene has no objects", path.display()),
+            SceneError::Mesh(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneFile {
+    image: ImageDescription,
+    camera: CameraDescription,
+    #[serde(default, alias = "background")]
+    environment: EnvironmentDescription,
+    #[serde(default)]
+    materials: HashMap<String, MaterialDescription>,
+    #[serde(default)]
+    objects: Vec<Spanned<ObjectEntry>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ImageDescription {
+    width: Spanned<usize>,
+    height: Spanned<usize>,
+    samples_per_pixel: Spanned<usize>,
+    max_depth: Spanned<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraDescription {
+    lookfrom: [f64; 3],
+    lookat: [f64; 3],
+    #[serde(default = "default_vup")]
+    vup: [f64; 3],
+    vfov: Spanned<f64>,
+    #[serde(default)]
+    aperture: f64,
+    /// Defaults to the distance from lookfrom to lookat.
+    focus_dist: Option<f64>,
+    #[serde(default)]
+    shutter_open: f64,
+    /// Defaults to shutter_open, freezing any motion.
+    shutter_close: Option<Spanned<f64>>,
+}
+
+fn default_vup() -> [f64; 3] {
+    [0.0, 1.0, 0.0]
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum EnvironmentDescription {
+    Solid {
+        color: [f64; 3],
+    },
+    Gradient {
+        bottom: [f64; 3],
+        top: [f64; 3],
+    },
+    Map {
+        path: PathBuf,
+        #[serde(default)]
+        rotation: f64,
+        #[serde(default = "default_intensity")]
+        intensity: f64,
+    },
+}
+
+fn default_intensity() -> f64 {
+    1.0
+}
+
+/// Scenes without an environment get the sky from the book.
+impl Default for EnvironmentDescription {
+    fn default() -> EnvironmentDescription {
+        EnvironmentDescription::Gradient {
+            bottom: [1.0, 1.0, 1.0],
+            top: [0.5, 0.7, 1.0],
+        }
+    }
+}
+
+impl EnvironmentDescription {
+    fn to_environment(&self, base: &Path) -> Result<Environment, SceneError> {
+        Ok(match self {
+            EnvironmentDescription::Solid { color } => Environment::Solid(vec3(color)),
+            EnvironmentDescription::Gradient { bottom, top } => Environment::Gradient {
+                bottom: vec3(bottom),
+                top: vec3(top),
+            },
+            EnvironmentDescription::Map {
+                path,
+                rotation,
+                intensity,
+            } => {
+                let path = base.join(path);
+                let image = Image::load(&path).map_err(|error| SceneError::Io { path, error })?;
+                Environment::Map(Arc::new(EnvironmentMap::make(image, *rotation, *intensity)))
+            }
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum MaterialDescription {
+    Lambertian {
+        albedo: ColorDescription,
+    },
+    Metal {
+        albedo: ColorDescription,
+        fuzz: f64,
+    },
+    Conductor {
+        #[serde(default = "default_metal")]
+        metal: MetalKind,
+        eta: Option<[f64; 3]>,
+        k: Option<[f64; 3]>,
+        #[serde(default)]
+        roughness: f64,
+    },
+    Dielectric {
+        index_of_refraction: f64,
+        #[serde(default)]
+        roughness: f64,
+    },
+    UniformScatterer {
+        albedo: ColorDescription,
+    },
+    DiffuseLight {
+        emit: ColorDescription,
+    },
+    Isotropic {
+        albedo: ColorDescription,
+    },
+    HenyeyGreenstein {
+        albedo: ColorDescription,
+        g: f64,
+    },
+}
+
+fn default_metal() -> MetalKind {
+    MetalKind::Aluminium
+}
+
+impl MaterialDescription {
+    /// Build the material. Image textures are loaded from paths relative to base, and noise
+    /// textures are generated with random numbers from the sampler.
+    fn to_material(
+        &self,
+        base: &Path,
+        sampler: &mut Sampler,
+    ) -> Result<Box<dyn Material>, SceneError> {
+        Ok(match self {
+            MaterialDescription::Lambertian { albedo } => {
+                Box::from(Lambertian::textured(albedo.to_texture(base, sampler)?))
+            }
+            MaterialDescription::Metal { albedo, fuzz } => {
+                Box::from(Metal::textured(albedo.to_texture(base, sampler)?, *fuzz))
+            }
+            MaterialDescription::Conductor {
+                metal,
+                eta,
+                k,
+                roughness,
+            } => {
+                let (metal_eta, metal_k) = metal.eta_k();
+                Box::from(Conductor::make(
+                    eta.as_ref().map_or(metal_eta, vec3),
+                    k.as_ref().map_or(metal_k, vec3),
+                    *roughness,
+                ))
+            }
+            MaterialDescription::Dielectric {
+                index_of_refraction,
+                roughness,
+            } => Box::from(Dielectric::rough(*index_of_refraction, *roughness)),
+            MaterialDescription::UniformScatterer { albedo } => Box::from(
+                UniformScatterer::textured(albedo.to_texture(base, sampler)?),
+            ),
+            MaterialDescription::DiffuseLight { emit } => {
+                Box::from(DiffuseLight::textured(emit.to_texture(base, sampler)?))
+            }
+            MaterialDescription::Isotropic { albedo } => {
+                Box::from(Isotropic::textured(albedo.to_texture(base, sampler)?))
+            }
+            MaterialDescription::HenyeyGreenstein { albedo, g } => Box::from(
+                HenyeyGreenstein::textured(albedo.to_texture(base, sampler)?, *g),
+            ),
+        })
+    }
+}
+
+/// A color for a material. Either a constant color written as an [r, g, b] array, or a table
+/// describing a texture.
+enum ColorDescription {
+    Constant([f64; 3]),
+    Texture(TextureDescription),
+}
+
+impl<'de> Deserialize<'de> for ColorDescription {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ColorVisitor;
+
+        impl<'de> Visitor<'de> for ColorVisitor {
+            type Value = ColorDescription;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "an [r, g, b] color or a texture table")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+                Deserialize::deserialize(SeqAccessDeserializer::new(seq))
+                    .map(ColorDescription::Constant)
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                Deserialize::deserialize(MapAccessDeserializer::new(map))
+                    .map(ColorDescription::Texture)
+            }
+        }
+
+        deserializer.deserialize_any(ColorVisitor)
+    }
+}
+
+impl ColorDescription {
+    fn to_texture(
+        &self,
+        base: &Path,
+        sampler: &mut Sampler,
+    ) -> Result<Box<dyn Texture>, SceneError> {
+        Ok(match self {
+            ColorDescription::Constant(color) => Box::from(SolidColor::make(vec3(color))),
+            ColorDescription::Texture(TextureDescription::Checker { scale, even, odd }) => {
+                Box::from(Checker::make(
+                    *scale,
+                    even.to_texture(base, sampler)?,
+                    odd.to_texture(base, sampler)?,
+                ))
+            }
+            ColorDescription::Texture(TextureDescription::Noise { scale, kind }) => {
+                Box::from(NoiseTexture::make(*scale, *kind, sampler))
+            }
+            ColorDescription::Texture(TextureDescription::Image { path }) => {
+                let path = base.join(path);
+                let image = Image::load(&path).map_err(|error| SceneError::Io { path, error })?;
+                Box::from(ImageTexture::make(image))
+            }
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum TextureDescription {
+    Checker {
+        scale: f64,
+        even: Box<ColorDescription>,
+        odd: Box<ColorDescription>,
+    },
+    Noise {
+        scale: f64,
+        #[serde(default = "default_noise_kind")]
+        kind: NoiseKind,
+    },
+    Image {
+        path: String,
+    },
+}
+
+fn default_noise_kind() -> NoiseKind {
+    NoiseKind::Marble
+}
+
+/// An object, with the transform placing it in the world.
+#[derive(Deserialize)]
+struct ObjectEntry {
+    #[serde(flatten)]
+    object: ObjectDescription,
+    /// Applied in order.
+    #[serde(default)]
+    transform: Vec<TransformDescription>,
+    /// The transform at time 1 for moving objects, blended with transform in between.
+    transform_end: Option<Vec<TransformDescription>>,
+    /// Fills the object with a medium of this density, its material being the phase function.
+    density: Option<Spanned<f64>>,
+    /// Varies the density of the medium filling a box, scaling it by the values of a grid.
+    grid: Option<GridDescription>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GridDescription {
+    path: PathBuf,
+    /// Needed for raw files, which don't give their own.
+    resolution: Option<[usize; 3]>,
+    /// The color of the glow given off by each unit of the emission grid.
+    emit: Option<[f64; 3]>,
+    /// Defaults to the density grid.
+    emission: Option<PathBuf>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum ObjectDescription {
+    Sphere {
+        center: [f64; 3],
+        /// Where a moving sphere ends up at time 1.
+        center_end: Option<[f64; 3]>,
+        /// Negative for a sphere turned inside out, such as the inside of a hollow glass ball.
+        radius: f64,
+        material: String,
+    },
+    Plane {
+        point: [f64; 3],
+        normal: [f64; 3],
+        material: String,
+    },
+    Quad {
+        corner: [f64; 3],
+        u: [f64; 3],
+        v: [f64; 3],
+        material: String,
+    },
+    Disk {
+        center: [f64; 3],
+        normal: [f64; 3],
+        radius: f64,
+        material: String,
+    },
+    Box {
+        min: [f64; 3],
+        max: [f64; 3],
+        material: String,
+    },
+    Mesh {
+        path: String,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum TransformDescription {
+    Translate([f64; 3]),
+    Scale([f64; 3]),
+    RotateX(f64),
+    RotateY(f64),
+    RotateZ(f64),
+    Rotate { axis: [f64; 3], angle: f64 },
+}
+
+impl TransformDescription {
+    /// The step, or None if it would squash objects flat.
+    fn to_step(&self) -> Option<TransformStep> {
+        let rotate = |axis: [f64; 3], angle: f64| TransformStep::Rotate {
+            axis: vec3(&axis),
+            angle,
+        };
+        Some(match self {
+            TransformDescription::Translate(offset) => TransformStep::Translate(vec3(offset)),
+            TransformDescription::Scale(factors) => {
+                if factors.contains(&0.0) {
+                    return None;
+                }
+                TransformStep::Scale(vec3(factors))
+            }
+            TransformDescription::RotateX(angle) => rotate([1.0, 0.0, 0.0], *angle),
+            TransformDescription::RotateY(angle) => rotate([0.0, 1.0, 0.0], *angle),
+            TransformDescription::RotateZ(angle) => rotate([0.0, 0.0, 1.0], *angle),
+            TransformDescription::Rotate { axis, angle } => {
+                if vec3(axis).near_zero() {
+                    return None;
+                }
+                rotate(*axis, *angle)
+            }
+        })
+    }
+}
+
+impl GridDescription {
+    /// Build the medium filling the boundary, with to_grid taking points in the world onto the
+    /// grid. Grids are loaded from paths relative to base.
+    fn to_medium(
+        &self,
+        base: &Path,
+        boundary: Box<dyn Hittable>,
+        to_grid: Transform,
+        density: f64,
+        phase: Box<dyn Material>,
+    ) -> Result<GridMedium, SceneError> {
+        let load = |path: &PathBuf| {
+            let path = base.join(path);
+            match VoxelGrid::load(&path, self.resolution) {
+                Ok(grid) => Ok(Arc::new(grid)),
+                Err(error) => Err(SceneError::Io { path, error }),
+            }
+        };
+        let densities = load(&self.path)?;
+        let medium = GridMedium::make(boundary, to_grid, densities.clone(), density, phase);
+        Ok(match &self.emit {
+            Some(emit) => {
+                let emission = match &self.emission {
+                    Some(path) => load(path)?,
+                    None => densities,
+                };
+                medium.with_emission(emission, vec3(emit))
+            }
+            None => medium,
+        })
+    }
+}
+
+fn vec3(v: &[f64; 3]) -> Vec3 {
+    Vec3 {
+        x: v[0],
+        y: v[1],
+        z: v[2],
+    }
+}
+
+/// Builds errors pointing at the place in the file that caused them.
+struct Validator<'a> {
+    path: &'a Path,
+    contents: &'a str,
+}
+
+impl<'a> Validator<'a> {
+    fn error(&self, span: Range<usize>, message: String) -> SceneError {
+        SceneError::Invalid {
+            path: self.path.to_path_buf(),
+            line: self.contents[..span.start].matches('\n').count() + 1,
+            message,
+        }
+    }
+
+    /// Check a value is positive, reporting an error on its line if not.
+    fn positive<T: PartialOrd + Default + Copy>(
+        &self,
+        value: &Spanned<T>,
+        name: &str,
+    ) -> Result<T, SceneError> {
+        if *value.get_ref() > T::default() {
+            Ok(*value.get_ref())
+        } else {
+            Err(self.error(value.span(), format!("{} must be greater than zero", name)))
+        }
+    }
+
+    /// Check the radius of a sphere is a finite number other than zero, reporting an error on
+    /// the object's line if not. Fields inside objects don't keep their own place in the file.
+    /// Negative radii turn the sphere inside out.
+    fn sphere_radius(&self, radius: f64, span: Range<usize>) -> Result<f64, SceneError> {
+        if radius.is_finite() && radius != 0.0 {
+            Ok(radius)
+        } else {
+            Err(self.error(
+                span,
+                "radius must be a finite number other than zero".to_string(),
+            ))
+        }
+    }
+
+    /// Check a direction an object faces along has a length that isn't zero or infinite, so it
+    /// can be normalized, reporting an error with the message on the object's line if not.
+    fn direction(
+        &self,
+        direction: Vec3,
+        span: Range<usize>,
+        message: &str,
+    ) -> Result<Vec3, SceneError> {
+        if direction.norm() > 0.0 && direction.norm().is_finite() {
+            Ok(direction)
+        } else {
+            Err(self.error(span, message.to_string()))
+        }
+    }
+
+    /// Check the radius of a disk is a finite number greater than zero, reporting an error on the
+    /// object's line if not.
+    fn disk_radius(&self, radius: f64, span: Range<usize>) -> Result<f64, SceneError> {
+        if radius.is_finite() && radius > 0.0 {
+            Ok(radius)
+        } else {
+            Err(self.error(
+                span,
+                "radius must be a finite number greater than zero".to_string(),
+            ))
+        }
+    }
+}
+
+/// Load a scene from a TOML file.
+pub fn load(path: &Path) -> Result<Scene, SceneError> {
+    let contents = fs::read_to_string(path).map_err(|error| SceneError::Io {
+        path: path.to_path_buf(),
+        error,
+    })?;
+    parse(path, &contents)
+}
+
+/// Build a scene from the contents of the TOML file at path, which other files it uses are
+/// found relative to.
+fn parse(path: &Path, contents: &str) -> Result<Scene, SceneError> {
+    let file: SceneFile = toml::from_str(contents).map_err(|error| SceneError::Parse {
+        path: path.to_path_buf(),
+        error,
+    })?;
+    let validator = Validator { path, contents };
+
+    let width = validator.positive(&file.image.width, "image.width")?;
+    let height = validator.positive(&file.image.height, "image.height")?;
+    let samples_per_pixel =
+        validator.positive(&file.image.samples_per_pixel, "image.samples_per_pixel")?;
+    let max_depth = validator.positive(&file.image.max_depth, "image.max_depth")?;
+
+    let vfov = *file.camera.vfov.get_ref();
+    if !(vfov > 0.0 && vfov < 180.0) {
+        return Err(validator.error(
+            file.camera.vfov.span(),
+            "camera.vfov must be between 0 and 180 degrees".to_string(),
+        ));
+    }
+    let shutter_open = file.camera.shutter_open;
+    let shutter_close = match &file.camera.shutter_close {
+        Some(close) if *close.get_ref() < shutter_open => {
+            return Err(validator.error(
+                close.span(),
+                "camera.shutter_close must not be before camera.shutter_open".to_string(),
+            ));
+        }
+        Some(close) => *close.get_ref(),
+        None => shutter_open,
+    };
+    let lookfrom = vec3(&file.camera.lookfrom);
+    let lookat = vec3(&file.camera.lookat);
+    let camera = CameraSettings {
+        lookfrom,
+        lookat,
+        vup: vec3(&file.camera.vup),
+        vfov,
+        aperture: file.camera.aperture,
+        focus_dist: file
+            .camera
+            .focus_dist
+            .unwrap_or_else(|| (lookfrom - lookat).magnitude()),
+        shutter_open,
+        shutter_close,
+    };
+
+    // Other files are found relative to the scene file.
+    let base = path.parent().unwrap_or_else(|| Path::new(""));
+    // A fixed seed, so a scene file always describes the same scene.
+    let mut sampler = Sampler::make(0);
+    let mut world = HittableList { hittables: vec![] };
+    // Objects are numbered from 1 in the order they are listed, and the scene's materials in
+    // order of name, for the ID AOVs. Each mesh's own materials are numbered after those.
+    let mut material_names: Vec<&String> = file.materials.keys().collect();
+    material_names.sort();
+    let mut next_material_id = material_names.len() + 1;
+    // Each mesh file is loaded once, however many objects use it, with the ID of its first
+    // material.
+    let mut meshes: HashMap<PathBuf, (Arc<dyn Hittable>, usize)> = HashMap::new();
+    for (index, object) in file.objects.iter().enumerate() {
+        let object_id = index + 1;
+        let entry = object.get_ref();
+        let steps = |descriptions: &[TransformDescription]| {
+            descriptions
+                .iter()
+                .map(|description| {
+                    description.to_step().ok_or_else(|| {
+                        validator.error(
+                            object.span(),
+                            "Transforms must not scale by zero or rotate about a zero axis"
+                                .to_string(),
+                        )
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let start = steps(&entry.transform)?;
+        let moving = entry.transform_end.is_some();
+        let motion = match &entry.transform_end {
+            Some(end) => Some(Motion::make(start.clone(), steps(end)?).ok_or_else(|| {
+                validator.error(
+                    object.span(),
+                    "transform_end must have the same steps as transform, in the same order, \
+                     turning about the same axes and not scaling through zero"
+                        .to_string(),
+                )
+            })?),
+            None => None,
+        };
+        // The material of an object filled with a medium, shared with the medium as its phase
+        // function.
+        let mut phase: Option<Arc<dyn Material>> = None;
+        // Looks up a named material for a single object, returning it with its ID.
+        let mut material_named = |material: &String| {
+            let description = file.materials.get(material).ok_or_else(|| {
+                validator.error(
+                    object.span(),
+                    format!("Material '{}' is not defined", material),
+                )
+            })?;
+            let mut built = description.to_material(base, &mut sampler)?;
+            if entry.density.is_some() {
+                let shared: Arc<dyn Material> = Arc::from(built);
+                phase = Some(shared.clone());
+                built = Box::from(shared);
+            }
+            Ok::<_, SceneError>((
+                built,
+                material_names.binary_search(&material).unwrap_or(0) + 1,
+            ))
+        };
+        let (hittable, material_id): (Box<dyn Hittable>, usize) = match &entry.object {
+            ObjectDescription::Sphere {
+                center,
+                center_end,
+                radius,
+                material,
+            } => {
+                let r = validator.sphere_radius(*radius, object.span())?;
+                let (material, material_id) = material_named(material)?;
+                let sphere: Box<dyn Hittable> = match center_end {
+                    Some(center_end) => Box::from(MovingSphere {
+                        center0: vec3(center),
+                        center1: vec3(center_end),
+                        r,
+                        material,
+                    }),
+                    None => Box::from(Sphere {
+                        center: vec3(center),
+                        r,
+                        material,
+                    }),
+                };
+                (sphere, material_id)
+            }
+            ObjectDescription::Plane {
+                point,
+                normal,
+                material,
+            } => {
+                let normal = validator.direction(
+                    vec3(normal),
+                    object.span(),
+                    "A plane's normal must not be zero",
+                )?;
+                let (material, material_id) = material_named(material)?;
+                let plane = Plane::make(vec3(point), normal, material);
+                (Box::from(plane), material_id)
+            }
+            ObjectDescription::Quad {
+                corner,
+                u,
+                v,
+                material,
+            } => {
+                let (u, v) = (vec3(u), vec3(v));
+                validator.direction(
+                    u.cross(&v),
+                    object.span(),
+                    "A quad's sides u and v must not be zero or parallel",
+                )?;
+                let (material, material_id) = material_named(material)?;
+                let quad = Quad::make(vec3(corner), u, v, material);
+                (Box::from(quad), material_id)
+            }
+            ObjectDescription::Disk {
+                center,
+                normal,
+                radius,
+                material,
+            } => {
+                let radius = validator.disk_radius(*radius, object.span())?;
+                let normal = validator.direction(
+                    vec3(normal),
+                    object.span(),
+                    "A disk's normal must not be zero",
+                )?;
+                let (material, material_id) = material_named(material)?;
+                let disk = Disk::make(vec3(center), normal, radius, material);
+                (Box::from(disk), material_id)
+            }
+            ObjectDescription::Box { min, max, material } => {
+                let (material, material_id) = material_named(material)?;
+                let cuboid = Cuboid::make(vec3(min), vec3(max), material);
+                (Box::from(cuboid), material_id)
+            }
+            ObjectDescription::Mesh { path: mesh_path } => {
+                let mesh_path = base.join(mesh_path);
+                if !meshes.contains_key(&mesh_path) {
+                    let mesh = obj::load(&mesh_path).map_err(SceneError::Mesh)?;
+                    let material_id = next_material_id;
+                    next_material_id += mesh.materials.len();
+                    let triangles = mesh.into_hittables();
+                    let shared: Arc<dyn Hittable> = if triangles.hittables.is_empty() {
+                        Arc::new(triangles)
+                    } else {
+                        Arc::new(BvhNode::make(triangles))
+                    };
+                    meshes.insert(mesh_path.clone(), (shared, material_id));
+                }
+                let (shared, material_id) = &meshes[&mesh_path];
+                (Box::from(shared.clone()), *material_id)
+            }
+        };
+        let hittable = match motion {
+            Some(motion) => Box::from(Instance::moving(Arc::from(hittable), motion)),
+            None if !start.is_empty() => Box::from(Instance::make(
+                Arc::from(hittable),
+                Transform::from_steps(&start),
+            )),
+            None => hittable,
+        };
+        // The object is only the boundary of the medium filling it, and isn't drawn itself.
+        let hittable: Box<dyn Hittable> = match (&entry.density, &entry.grid) {
+            (Some(density), grid) => {
+                let density = validator.positive(density, "density")?;
+                let phase = phase.ok_or_else(|| {
+                    validator.error(
+                        object.span(),
+                        "Meshes can't be filled with a medium".to_string(),
+                    )
+                })?;
+                let medium: Box<dyn Medium> = match grid {
+                    None => Box::from(ConstantMedium::make(hittable, density, Box::from(phase))),
+                    Some(grid) => {
+                        let error =
+                            |message: &str| validator.error(object.span(), message.to_string());
+                        let (min, max) = match &entry.object {
+                            ObjectDescription::Box { min, max, .. } => (vec3(min), vec3(max)),
+                            _ => return Err(error("Voxel grids can only fill boxes")),
+                        };
+                        if moving {
+                            return Err(error("Voxel grids can't move"));
+                        }
+                        let corner = Vec3 {
+                            x: min.x.min(max.x),
+                            y: min.y.min(max.y),
+                            z: min.z.min(max.z),
+                        };
+                        let size = Vec3 {
+                            x: (max.x - min.x).abs(),
+                            y: (max.y - min.y).abs(),
+                            z: (max.z - min.z).abs(),
+                        };
+                        if size.x == 0.0 || size.y == 0.0 || size.z == 0.0 {
+                            return Err(error("A box filled with a voxel grid must not be flat"));
+                        }
+                        // From the world into the box, then the box onto the grid's unit cube.
+                        let to_grid = Transform::from_steps(&start)
+                            .inverse()
+                            .then(&Transform::translate(-corner))
+                            .then(&Transform::scale(Vec3 {
+                                x: 1.0 / size.x,
+                                y: 1.0 / size.y,
+                                z: 1.0 / size.z,
+                            }));
+                        if grid.emit.is_none() && grid.emission.is_some() {
+                            return Err(error("A grid's emission needs an emit color"));
+                        }
+                        let medium =
+                            grid.to_medium(base, hittable, to_grid, density, Box::from(phase))?;
+                        Box::from(medium)
+                    }
+                };
+                Box::from(Volume::make(medium))
+            }
+            (None, Some(_)) => {
+                return Err(validator.error(
+                    object.span(),
+                    "A voxel grid needs a density to scale it by".to_string(),
+                ))
+            }
+            (None, None) => hittable,
+        };
+        world.hittables.push(Box::from(Labelled {
+            hittable,
+            object_id,
+            material_id,
+        }));
+    }
+    if world.hittables.is_empty() {
+        return Err(SceneError::Empty {
+            path: path.to_path_buf(),
+        });
+    }
+
+    Ok(Scene {
+        camera,
+        world,
+        environment: file.environment.to_environment(base)?,
+        width,
+        height,
+        samples_per_pixel,
+        max_depth,
+    })
+}
+
+/// The scene from the end of the book: a random scattering of small spheres around three big
+/// ones. The small spheres are placed using random numbers from the sampler.
+pub fn default_scene(sampler: &mut Sampler) -> Scene {
+    // Camera
+    let lookfrom = Vec3 {
+        x: 13.0,
+        y: 2.0,
+        z: 3.0,
+    };
+    let lookat = Vec3 {
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+    };
+    let camera = CameraSettings {
+        lookfrom,
+        lookat,
+        vup: Vec3 {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        },
+        vfov: 20.0,
+        aperture: 0.1,
+        focus_dist: 10.0,
+        shutter_open: 0.0,
+        shutter_close: 0.0,
+    };
+
+    Scene {
+        camera,
+        world: random_scene(sampler),
+        environment: Environment::sky(),
+        width: WIDTH,
+        height: HEIGHT,
+        samples_per_pixel: SAMPLES_PER_PIXEL,
+        max_depth: MAX_DEPTH,
+    }
+}
+
+fn random_scene(sampler: &mut Sampler) -> HittableList {
+    let mut world = HittableList { hittables: vec![] };
+
+    // Define our materials
+    let material_ground = UniformScatterer::make(Vec3 {
+        x: 0.5,
+        y: 0.5,
+        z: 0.5,
+    });
+    world.hittables.push(Box::from(Plane::make(
+        Vec3::zero(),
+        Vec3 {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        },
+        Box::from(material_ground),
+    )));
+
+    for a in -11..11 {
+        for b in -11..11 {
+            let choose_mat = sampler.random_double();
+            let center = Vec3 {
+                x: a as f64 + 0.9 * sampler.random_double(),
+                y: 0.2,
+                z: b as f64 + 0.9 * sampler.random_double(),
+            };
+            let diff = Vec3 {
+                x: 4.0,
+                y: 0.2,
+                z: 0.0,
+            };
+            if (center - diff).magnitude() > 0.9 {
+                world.hittables.push(Box::from(Sphere {
+                    center,
+                    r: 0.2,
+                    material: match choose_mat {
+                        x if x < 0.8 => {
+                            // diffuse
+                            Box::from(Lambertian::make(
+                                Vec3::random(sampler, 0.0, 1.0) * Vec3::random(sampler, 0.0, 1.0),
+                            ))
+                        }
+                        x if (0.8..0.95).contains(&x) => {
+                            // metal
+                            Box::from(Metal::make(
+                                Vec3::random(sampler, 0.5, 1.0),
+                                sampler.random_double() / 2.0,
+                            ))
+                        }
+                        _ => {
+                            // glass
+                            Box::from(Dielectric::make(1.5))
+                        }
+                    },
+                }));
+            }
+        }
+    }
+
+    world.hittables.push(Box::from(Sphere {
+        center: Vec3 {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        },
+        r: 1.0,
+        material: Box::from(Dielectric::make(1.5)),
+    }));
+    world.hittables.push(Box::from(Sphere {
+        center: Vec3 {
+            x: -4.0,
+            y: 1.0,
+            z: 0.0,
+        },
+        r: 1.0,
+        material: Box::from(Lambertian::make(Vec3 {
+            x: 0.4,
+            y: 0.2,
+            z: 0.1,
+        })),
+    }));
+    world.hittables.push(Box::from(Sphere {
+        center: Vec3 {
+            x: 4.0,
+            y: 1.0,
+            z: 0.0,
+        },
+        r: 1.0,
+        material: Box::from(Metal::make(
+            Vec3 {
+                x: 0.7,
+                y: 0.6,
+                z: 0.5,
+            },
+            0.0,
+        )),
+    }));
+    // Every sphere is its own object, with its own material.
+    HittableList {
+        hittables: world
+            .hittables
+            .into_iter()
+            .enumerate()
+            .map(|(index, sphere)| {
+                Box::from(Labelled {
+                    hittable: sphere,
+                    object_id: index + 1,
+                    material_id: index + 1,
+                }) as Box<dyn Hittable>
+            })
+            .collect(),
+    }
+}
+
+/// A small scene for the tests to break, one line at a time.
+#[cfg(test)]
+const TEST_SCENE: &str = r#"[image]
+width = 4
+height = 3
+samples_per_pixel = 1
+max_depth = 5
+
+[camera]
+lookfrom = [0.0, 0.0, 5.0]
+lookat = [0.0, 0.0, 0.0]
+vfov = 40.0
+
+[materials.white]
+type = "lambertian"
+albedo = [0.5, 0.5, 0.5]
+
+[[objects]]
+type = "sphere"
+center = [0.0, 0.0, 0.0]
+radius = 1.0
+material = "white"
+"#;
+
+/// The test scene with one line replaced and more added to the end, which must fail to load,
+/// returning the line it fails on and why.
+#[cfg(test)]
+fn error_loading(line: &str, replacement: &str, more: &str) -> (usize, String) {
+    assert!(TEST_SCENE.contains(line));
+    let contents = TEST_SCENE.replace(line, replacement) + more;
+    match parse(Path::new("test.toml"), &contents) {
+        Err(SceneError::Invalid { line, message, .. }) => (line, message),
+        Err(error) => panic!("The scene failed to load for the wrong reason: {}", error),
+        Ok(_) => panic!("The scene loaded"),
+    }
+}
+
+#[test]
+fn test_scene_errors_point_at_the_object() {
+    assert!(parse(Path::new("test.toml"), TEST_SCENE).is_ok());
+
+    assert_eq!(
+        (16, "Material 'black' is not defined".to_string()),
+        error_loading("material = \"white\"", "material = \"black\"", "")
+    );
+    assert_eq!(
+        (
+            16,
+            "radius must be a finite number other than zero".to_string()
+        ),
+        error_loading("radius = 1.0", "radius = 0.0", "")
+    );
+    // Unlike spheres, disks can't be turned inside out.
+    let disk = r#"
+[[objects]]
+type = "disk"
+center = [0.0, 0.0, 0.0]
+normal = [0.0, 0.0, 1.0]
+radius = -1.0
+material = "white"
+"#;
+    assert!(parse(
+        Path::new("test.toml"),
+        &TEST_SCENE.replace("radius = 1.0", "radius = -1.0")
+    )
+    .is_ok());
+    assert_eq!(
+        (
+            22,
+            "radius must be a finite number greater than zero".to_string()
+        ),
+        error_loading("", "", disk)
+    );
+    // Flat objects need a direction to face.
+    let plane = r#"
+[[objects]]
+type = "plane"
+point = [0.0, 0.0, 0.0]
+normal = [0.0, 0.0, 0.0]
+material = "white"
+"#;
+    assert_eq!(
+        (22, "A plane's normal must not be zero".to_string()),
+        error_loading("", "", plane)
+    );
+    let quad = r#"
+[[objects]]
+type = "quad"
+corner = [0.0, 0.0, 0.0]
+u = [1.0, 0.0, 0.0]
+v = [-2.0, 0.0, 0.0]
+material = "white"
+"#;
+    assert_eq!(
+        (
+            22,
+            "A quad's sides u and v must not be zero or parallel".to_string()
+        ),
+        error_loading("", "", quad)
+    );
+    assert_eq!(
+        (22, "A disk's normal must not be zero".to_string()),
+        error_loading(
+            "",
+            "",
+            &disk
+                .replace("radius = -1.0", "radius = 1.0")
+                .replace("[0.0, 0.0, 1.0]", "[0.0, 0.0, 0.0]")
+        )
+    );
+    let (line, message) = error_loading(
+        "",
+        "",
+        "transform = [{ translate = [1.0, 0.0, 0.0] }]\n\
+         transform_end = [{ rotate_y = 30.0 }]\n",
+    );
+    assert_eq!(16, line);
+    assert!(message.starts_with("transform_end must have the same steps as transform"));
+}
+
+#[test]
+fn test_scene_errors_point_at_the_setting() {
+    assert_eq!(
+        (
+            10,
+            "camera.vfov must be between 0 and 180 degrees".to_string()
+        ),
+        error_loading("vfov = 40.0", "vfov = 180.0", "")
+    );
+    assert_eq!(10, error_loading("vfov = 40.0", "vfov = nan", "").0);
+    assert_eq!(
+        (2, "image.width must be greater than zero".to_string()),
+        error_loading("width = 4", "width = 0", "")
+    );
+    assert_eq!(
+        (5, "image.max_depth must be greater than zero".to_string()),
+        error_loading("max_depth = 5", "max_depth = 0", "")
+    );
+}
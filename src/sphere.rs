@@ -1,35 +1,127 @@
+use crate::aabb::Aabb;
 use crate::hit::HitOrMiss::Miss;
 use crate::hit::{HitOrMiss, Hittable};
+use crate::light::{Light, SphereLight};
 use crate::material::Material;
 use crate::ray::Ray;
 use crate::vec3::Vec3;
 
 /// A Sphere defined with a center point and a radius.
 pub struct Sphere {
+    /// The center of the sphere.
     pub center: Vec3,
+    /// The radius of the sphere.
     pub r: f64,
+    /// What the sphere is made of.
     pub material: Box<dyn Material>,
 }
 
-impl Hittable for Sphere {
-    /// Test whether a given ray hit the sphere between times t_min and t_max.
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> HitOrMiss {
-        let oc = ray.origin - self.center;
+impl Sphere {
+    /// The texture coordinates of a point on a unit sphere centered at the origin. u runs around
+    /// the sphere from -x, and v from the bottom of the sphere to the top.
+    fn uv(p: Vec3) -> (f64, f64) {
+        let theta = (-p.y).acos();
+        let phi = (-p.z).atan2(p.x) + std::f64::consts::PI;
+        (
+            phi / (2.0 * std::f64::consts::PI),
+            theta / std::f64::consts::PI,
+        )
+    }
+
+    /// Test whether a given ray hits a sphere with the given center, radius and material between
+    /// times t_min and t_max. Shared with MovingSphere, which is a sphere wherever it is.
+    pub fn hit_at<'a>(
+        center: Vec3,
+        r: f64,
+        material: &'a dyn Material,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+    ) -> HitOrMiss<'a> {
+        let oc = ray.origin - center;
         let a = ray.direction.norm();
         let h = oc.dot(&ray.direction);
-        let c = oc.norm() - self.r * self.r;
+        let c = oc.norm() - r * r;
         let discriminant = h * h - a * c;
-        return if discriminant < 0.0 {
+        if discriminant < 0.0 {
             Miss
         } else {
             let sqrt_d = discriminant.sqrt();
 
-            let root = (-h - sqrt_d) / a;
+            // Try the near root first, then the far one, which is the one hit from inside.
+            let mut root = (-h - sqrt_d) / a;
             if root < t_min || t_max < root {
-                return Miss;
+                root = (-h + sqrt_d) / a;
+                if root < t_min || t_max < root {
+                    return Miss;
+                }
             }
             let p = ray.at(root);
-            HitOrMiss::hit(p, (p - self.center) / self.r, root, ray, &*self.material)
-        };
+            let outward_normal = (p - center) / r;
+            HitOrMiss::hit(
+                p,
+                outward_normal,
+                root,
+                Sphere::uv(outward_normal),
+                ray,
+                material,
+            )
+        }
+    }
+}
+
+impl Hittable for Sphere {
+    /// Test whether a given ray hit the sphere between times t_min and t_max.
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> HitOrMiss<'_> {
+        Sphere::hit_at(self.center, self.r, &*self.material, ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let radius = Vec3::from_one(self.r.abs());
+        Some(Aabb {
+            min: self.center - radius,
+            max: self.center + radius,
+        })
+    }
+
+    fn lights(&self) -> Vec<Box<dyn Light>> {
+        if self.material.emits_light() {
+            vec![Box::from(SphereLight {
+                center: self.center,
+                r: self.r,
+            })]
+        } else {
+            vec![]
+        }
+    }
+}
+
+#[test]
+fn test_ray_from_inside_sphere_hits_its_far_side() {
+    use crate::material::lambertian::Lambertian;
+
+    let sphere = Sphere {
+        center: Vec3::zero(),
+        r: 2.0,
+        material: Box::from(Lambertian::make(Vec3::from_one(0.5))),
+    };
+    let ray = Ray {
+        origin: Vec3::zero(),
+        direction: Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: 0.5,
+        },
+        time: 0.0,
+    };
+    match sphere.hit(&ray, 0.001, f64::INFINITY) {
+        HitOrMiss::Hit { t, point, .. } => {
+            assert!((t - 4.0).abs() < 1e-9);
+            assert!(!point.front_face);
+            assert!((point.normal.z + 1.0).abs() < 1e-9);
+        }
+        Miss => panic!("The ray left the sphere without hitting it"),
     }
+    // Past the far side there is nothing left to hit.
+    assert!(matches!(sphere.hit(&ray, 4.001, f64::INFINITY), Miss));
 }
@@ -0,0 +1,19 @@
+use crate::vec3::Vec3;
+
+/// A 3D checkerboard of two other textures.
+pub mod checker;
+/// Images wrapped around surfaces.
+pub mod image_texture;
+/// Perlin noise.
+pub mod noise;
+/// A single color.
+pub mod solid_color;
+
+/// A color that varies over a surface, looked up by the texture coordinates (u, v) of a point on
+/// the surface, or by the point p itself.
+///
+/// Textures are shared between render threads, so must be Send and Sync.
+pub trait Texture: Send + Sync {
+    /// The color of the texture at texture coordinates (u, v) and point p.
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3;
+}
@@ -0,0 +1,35 @@
+use crate::texture::Texture;
+use crate::vec3::Vec3;
+
+/// A Texture that alternates between two other textures in a 3D checkerboard of cubes. Since it
+/// depends on the point in space rather than on texture coordinates, it looks the same on any
+/// surface.
+pub struct Checker {
+    inv_scale: f64,
+    even: Box<dyn Texture>,
+    odd: Box<dyn Texture>,
+}
+
+impl Checker {
+    /// Create a checkerboard of cubes with sides of length scale.
+    pub fn make(scale: f64, even: Box<dyn Texture>, odd: Box<dyn Texture>) -> Checker {
+        Checker {
+            inv_scale: scale.recip(),
+            even,
+            odd,
+        }
+    }
+}
+
+impl Texture for Checker {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        let cube = (self.inv_scale * p.x).floor()
+            + (self.inv_scale * p.y).floor()
+            + (self.inv_scale * p.z).floor();
+        if cube.rem_euclid(2.0) < 1.0 {
+            self.even.value(u, v, p)
+        } else {
+            self.odd.value(u, v, p)
+        }
+    }
+}
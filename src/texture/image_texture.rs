@@ -0,0 +1,26 @@
+use crate::image::Image;
+use crate::texture::Texture;
+use crate::vec3::Vec3;
+
+/// A Texture that wraps an image around a surface according to its texture coordinates.
+pub struct ImageTexture {
+    image: Image,
+}
+
+impl ImageTexture {
+    /// Create a texture showing the image.
+    pub fn make(image: Image) -> ImageTexture {
+        ImageTexture { image }
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: Vec3) -> Vec3 {
+        // Texture coordinates start from the bottom left, but image rows from the top.
+        let u = u.clamp(0.0, 1.0);
+        let v = 1.0 - v.clamp(0.0, 1.0);
+        let i = ((u * self.image.width as f64) as usize).min(self.image.width - 1);
+        let j = ((v * self.image.height as f64) as usize).min(self.image.height - 1);
+        self.image.get(i, j)
+    }
+}
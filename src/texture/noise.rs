@@ -0,0 +1,147 @@
+use serde::Deserialize;
+
+use crate::sampler::Sampler;
+use crate::texture::Texture;
+use crate::vec3::Vec3;
+
+const POINT_COUNT: usize = 256;
+
+/// Ken Perlin's gradient noise. A smoothly varying random value at every point in space, built
+/// by interpolating between random gradients placed on the corners of a lattice of unit cubes.
+pub struct Perlin {
+    gradients: Vec<Vec3>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+
+impl Perlin {
+    /// Create a Perlin noise generator, with gradients and permutations drawn from the sampler.
+    pub fn make(sampler: &mut Sampler) -> Perlin {
+        Perlin {
+            gradients: (0..POINT_COUNT)
+                .map(|_| Vec3::random(sampler, -1.0, 1.0).normalize())
+                .collect(),
+            perm_x: Perlin::permutation(sampler),
+            perm_y: Perlin::permutation(sampler),
+            perm_z: Perlin::permutation(sampler),
+        }
+    }
+
+    /// A random shuffle of the numbers 0 to POINT_COUNT - 1.
+    fn permutation(sampler: &mut Sampler) -> Vec<usize> {
+        let mut p: Vec<usize> = (0..POINT_COUNT).collect();
+        for i in (1..POINT_COUNT).rev() {
+            let target = (sampler.random_double() * (i + 1) as f64) as usize;
+            p.swap(i, target);
+        }
+        p
+    }
+
+    /// The noise at point p, in the range [-1, 1].
+    pub fn noise(&self, p: Vec3) -> f64 {
+        let (u, v, w) = (p.x - p.x.floor(), p.y - p.y.floor(), p.z - p.z.floor());
+        let (i, j, k) = (p.x.floor() as i64, p.y.floor() as i64, p.z.floor() as i64);
+
+        // Hermite smoothing of the position within the cube removes grid artifacts.
+        let (uu, vv, ww) = (
+            u * u * (3.0 - 2.0 * u),
+            v * v * (3.0 - 2.0 * v),
+            w * w * (3.0 - 2.0 * w),
+        );
+        let mut accum = 0.0;
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    let gradient = self.gradients[self.perm_x[((i + di) & 255) as usize]
+                        ^ self.perm_y[((j + dj) & 255) as usize]
+                        ^ self.perm_z[((k + dk) & 255) as usize]];
+                    let (fi, fj, fk) = (di as f64, dj as f64, dk as f64);
+                    let weight = Vec3 {
+                        x: u - fi,
+                        y: v - fj,
+                        z: w - fk,
+                    };
+                    accum += (fi * uu + (1.0 - fi) * (1.0 - uu))
+                        * (fj * vv + (1.0 - fj) * (1.0 - vv))
+                        * (fk * ww + (1.0 - fk) * (1.0 - ww))
+                        * gradient.dot(&weight);
+                }
+            }
+        }
+        accum
+    }
+
+    /// The sum of several octaves of noise, each at double the frequency and half the weight of
+    /// the last. Gives a rougher, more natural looking result than noise alone.
+    pub fn turbulence(&self, p: Vec3, depth: usize) -> f64 {
+        let mut accum = 0.0;
+        let mut temp_p = p;
+        let mut weight = 1.0;
+        for _ in 0..depth {
+            accum += weight * self.noise(temp_p);
+            weight *= 0.5;
+            temp_p = temp_p * 2.0;
+        }
+        accum.abs()
+    }
+}
+
+/// The ways a NoiseTexture can turn Perlin noise into a color.
+#[derive(Copy, Clone, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoiseKind {
+    /// Soft, blurry blobs.
+    Smooth,
+    /// Rough, cloudy patterns.
+    Turbulence,
+    /// Stripes along the z axis, distorted by turbulence.
+    Marble,
+}
+
+/// A grey Texture with brightness varying according to Perlin noise.
+pub struct NoiseTexture {
+    perlin: Perlin,
+    scale: f64,
+    kind: NoiseKind,
+}
+
+impl NoiseTexture {
+    /// Create a noise texture. Larger scales give smaller features. The pattern of the noise
+    /// depends on the random numbers drawn from the sampler.
+    pub fn make(scale: f64, kind: NoiseKind, sampler: &mut Sampler) -> NoiseTexture {
+        NoiseTexture {
+            perlin: Perlin::make(sampler),
+            scale,
+            kind,
+        }
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: f64, _v: f64, p: Vec3) -> Vec3 {
+        let brightness = match self.kind {
+            NoiseKind::Smooth => 0.5 * (1.0 + self.perlin.noise(self.scale * p)),
+            NoiseKind::Turbulence => self.perlin.turbulence(self.scale * p, 7),
+            NoiseKind::Marble => {
+                0.5 * (1.0 + (self.scale * p.z + 10.0 * self.perlin.turbulence(p, 7)).sin())
+            }
+        };
+        Vec3::from_one(brightness)
+    }
+}
+
+#[test]
+fn test_perlin_noise_is_zero_on_lattice_points() {
+    let perlin = Perlin::make(&mut Sampler::make(0));
+    for p in [
+        Vec3::zero(),
+        Vec3 {
+            x: 3.0,
+            y: -2.0,
+            z: 7.0,
+        },
+    ] {
+        assert!(perlin.noise(p).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,20 @@
+use crate::texture::Texture;
+use crate::vec3::Vec3;
+
+/// A Texture that is the same color everywhere.
+pub struct SolidColor {
+    color: Vec3,
+}
+
+impl SolidColor {
+    /// Create a texture of the given color.
+    pub fn make(color: Vec3) -> SolidColor {
+        SolidColor { color }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _p: Vec3) -> Vec3 {
+        self.color
+    }
+}
@@ -0,0 +1,487 @@
+use crate::aabb::Aabb;
+use crate::vec3::Vec3;
+
+/// A 4x4 matrix, as rows.
+type Matrix = [[f64; 4]; 4];
+
+const IDENTITY: Matrix = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+/// An affine transform of space: any combination of translations, rotations and scales, held as
+/// a 4x4 matrix along with its inverse so that both ways are cheap to apply.
+///
+/// Transforms are combined with then, so a transform that scales an object, turns it and then
+/// moves it into place reads in that order:
+///
+/// ```
+/// use rays::transform::Transform;
+/// use rays::Vec3;
+///
+/// let transform = Transform::scale(Vec3::from_one(2.0))
+///     .then(&Transform::rotate_y(90.0))
+///     .then(&Transform::translate(Vec3 { x: 0.0, y: 1.0, z: 0.0 }));
+/// let p = transform.point(Vec3 { x: 1.0, y: 0.0, z: 0.0 });
+/// assert!((p - Vec3 { x: 0.0, y: 1.0, z: -2.0 }).magnitude() < 1e-12);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Transform {
+    matrix: Matrix,
+    inverse: Matrix,
+}
+
+impl Transform {
+    /// The transform that leaves everything where it is.
+    pub fn identity() -> Transform {
+        Transform {
+            matrix: IDENTITY,
+            inverse: IDENTITY,
+        }
+    }
+
+    /// Move everything by offset.
+    pub fn translate(offset: Vec3) -> Transform {
+        let matrix = |d: Vec3| {
+            let mut m = IDENTITY;
+            m[0][3] = d.x;
+            m[1][3] = d.y;
+            m[2][3] = d.z;
+            m
+        };
+        Transform {
+            matrix: matrix(offset),
+            inverse: matrix(-offset),
+        }
+    }
+
+    /// Stretch everything away from the origin by a factor along each axis. Negative factors
+    /// mirror. No factor may be zero.
+    pub fn scale(factors: Vec3) -> Transform {
+        let matrix = |s: Vec3| {
+            let mut m = IDENTITY;
+            m[0][0] = s.x;
+            m[1][1] = s.y;
+            m[2][2] = s.z;
+            m
+        };
+        Transform {
+            matrix: matrix(factors),
+            inverse: matrix(Vec3 {
+                x: 1.0 / factors.x,
+                y: 1.0 / factors.y,
+                z: 1.0 / factors.z,
+            }),
+        }
+    }
+
+    /// Turn everything by angle degrees about an axis through the origin, anticlockwise when
+    /// looking back along the axis towards the origin.
+    pub fn rotate(axis: Vec3, angle: f64) -> Transform {
+        let a = axis.normalize();
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let k = 1.0 - cos;
+        let matrix = [
+            [
+                cos + a.x * a.x * k,
+                a.x * a.y * k - a.z * sin,
+                a.x * a.z * k + a.y * sin,
+                0.0,
+            ],
+            [
+                a.y * a.x * k + a.z * sin,
+                cos + a.y * a.y * k,
+                a.y * a.z * k - a.x * sin,
+                0.0,
+            ],
+            [
+                a.z * a.x * k - a.y * sin,
+                a.z * a.y * k + a.x * sin,
+                cos + a.z * a.z * k,
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ];
+        // A rotation's inverse is its transpose.
+        Transform {
+            matrix,
+            inverse: transpose(&matrix),
+        }
+    }
+
+    /// Turn everything by angle degrees about the x axis.
+    pub fn rotate_x(angle: f64) -> Transform {
+        Transform::rotate(
+            Vec3 {
+                x: 1.0,
+                y: 0.0,
+                z: 0.0,
+            },
+            angle,
+        )
+    }
+
+    /// Turn everything by angle degrees about the y axis, anticlockwise seen from above.
+    pub fn rotate_y(angle: f64) -> Transform {
+        Transform::rotate(
+            Vec3 {
+                x: 0.0,
+                y: 1.0,
+                z: 0.0,
+            },
+            angle,
+        )
+    }
+
+    /// Turn everything by angle degrees about the z axis.
+    pub fn rotate_z(angle: f64) -> Transform {
+        Transform::rotate(
+            Vec3 {
+                x: 0.0,
+                y: 0.0,
+                z: 1.0,
+            },
+            angle,
+        )
+    }
+
+    /// The transform that applies this one and then next.
+    pub fn then(&self, next: &Transform) -> Transform {
+        Transform {
+            matrix: multiply(&next.matrix, &self.matrix),
+            inverse: multiply(&self.inverse, &next.inverse),
+        }
+    }
+
+    /// The transform that undoes this one.
+    pub fn inverse(&self) -> Transform {
+        Transform {
+            matrix: self.inverse,
+            inverse: self.matrix,
+        }
+    }
+
+    /// Move a point.
+    pub fn point(&self, p: Vec3) -> Vec3 {
+        apply(&self.matrix, p, 1.0)
+    }
+
+    /// Transform a direction or offset, which turns and stretches but doesn't move.
+    pub fn vector(&self, v: Vec3) -> Vec3 {
+        apply(&self.matrix, v, 0.0)
+    }
+
+    /// Transform a surface normal, which has to stay at right angles to the surface however it
+    /// is stretched, so goes by the transpose of the inverse. The result isn't normalized.
+    pub fn normal(&self, n: Vec3) -> Vec3 {
+        let m = &self.inverse;
+        Vec3 {
+            x: m[0][0] * n.x + m[1][0] * n.y + m[2][0] * n.z,
+            y: m[0][1] * n.x + m[1][1] * n.y + m[2][1] * n.z,
+            z: m[0][2] * n.x + m[1][2] * n.y + m[2][2] * n.z,
+        }
+    }
+
+    /// The transform that applies each step in turn.
+    pub fn from_steps(steps: &[TransformStep]) -> Transform {
+        steps
+            .iter()
+            .fold(Transform::identity(), |t, step| t.then(&step.transform()))
+    }
+
+    /// The box around a transformed box: the box around its eight transformed corners.
+    pub fn bounding_box(&self, bbox: &Aabb) -> Aabb {
+        let corners = self.corners(bbox);
+        corners[1..].iter().fold(
+            Aabb {
+                min: corners[0],
+                max: corners[0],
+            },
+            |b, &corner| b.expand(corner),
+        )
+    }
+
+    /// The eight corners of a box, transformed.
+    fn corners(&self, bbox: &Aabb) -> [Vec3; 8] {
+        let mut corners = [Vec3::zero(); 8];
+        for (i, corner) in corners.iter_mut().enumerate() {
+            *corner = self.point(Vec3 {
+                x: if i & 1 == 0 { bbox.min.x } else { bbox.max.x },
+                y: if i & 2 == 0 { bbox.min.y } else { bbox.max.y },
+                z: if i & 4 == 0 { bbox.min.z } else { bbox.max.z },
+            });
+        }
+        corners
+    }
+
+    /// How much the transform scales volumes by: the determinant of its linear part. Negative if
+    /// it mirrors.
+    pub fn determinant(&self) -> f64 {
+        let m = &self.matrix;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+}
+
+/// One step of a transform, described by numbers that can be blended with those of another step
+/// of the same kind, for objects that move.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TransformStep {
+    /// Move by an offset.
+    Translate(Vec3),
+    /// Stretch by a factor along each axis.
+    Scale(Vec3),
+    /// Turn by angle degrees about an axis through the origin.
+    Rotate {
+        /// The axis to turn about.
+        axis: Vec3,
+        /// How far to turn, in degrees.
+        angle: f64,
+    },
+}
+
+impl TransformStep {
+    /// The transform for the step.
+    pub fn transform(&self) -> Transform {
+        match *self {
+            TransformStep::Translate(offset) => Transform::translate(offset),
+            TransformStep::Scale(factors) => Transform::scale(factors),
+            TransformStep::Rotate { axis, angle } => Transform::rotate(axis, angle),
+        }
+    }
+
+    /// The step a fraction of the way from this one to end. None if they can't be blended:
+    /// steps of different kinds, turns about different axes, or scales that would pass through
+    /// zero.
+    pub fn lerp(&self, end: &TransformStep, fraction: f64) -> Option<TransformStep> {
+        let mix = |a: Vec3, b: Vec3| a + fraction * (b - a);
+        match (*self, *end) {
+            (TransformStep::Translate(a), TransformStep::Translate(b)) => {
+                Some(TransformStep::Translate(mix(a, b)))
+            }
+            (TransformStep::Scale(a), TransformStep::Scale(b)) => {
+                if (0..3).all(|i| a[i] * b[i] > 0.0) {
+                    Some(TransformStep::Scale(mix(a, b)))
+                } else {
+                    None
+                }
+            }
+            (
+                TransformStep::Rotate { axis, angle: a },
+                TransformStep::Rotate {
+                    axis: end_axis,
+                    angle: b,
+                },
+            ) if axis == end_axis => Some(TransformStep::Rotate {
+                axis,
+                angle: a + fraction * (b - a),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// A transform that changes while the shutter is open, going from one list of steps at time 0 to
+/// another at time 1 by blending each step with the one at the same place in the other list. So
+/// objects move in straight lines, grow steadily and turn at a steady rate about fixed axes,
+/// which blending the matrices themselves wouldn't do. Before time 0 and after time 1, they stay
+/// put.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Motion {
+    start: Vec<TransformStep>,
+    end: Vec<TransformStep>,
+}
+
+impl Motion {
+    /// The motion from the steps at time 0 to those at time 1. None if the lists differ in
+    /// length, or any pair of steps can't be blended.
+    pub fn make(start: Vec<TransformStep>, end: Vec<TransformStep>) -> Option<Motion> {
+        if start.len() != end.len()
+            || start
+                .iter()
+                .zip(&end)
+                .any(|(a, b)| a.lerp(b, 0.0).is_none())
+        {
+            return None;
+        }
+        Some(Motion { start, end })
+    }
+
+    /// The transform at the given time.
+    pub fn at(&self, time: f64) -> Transform {
+        let fraction = time.clamp(0.0, 1.0);
+        self.start
+            .iter()
+            .zip(&self.end)
+            .fold(Transform::identity(), |t, (a, b)| {
+                // Steps were checked to blend when the motion was made.
+                t.then(&a.lerp(b, fraction).unwrap().transform())
+            })
+    }
+
+    /// A box around everywhere a box goes as it moves. The boxes around it at many moments are
+    /// joined together and then grown by the furthest a corner moves between moments, which
+    /// covers the curved paths of turning corners in between.
+    pub fn bounding_box(&self, bbox: &Aabb) -> Aabb {
+        const MOMENTS: usize = 64;
+        let start = self.at(0.0);
+        let mut result = start.bounding_box(bbox);
+        let mut previous = start.corners(bbox);
+        let mut furthest: f64 = 0.0;
+        for i in 1..=MOMENTS {
+            let transform = self.at(i as f64 / MOMENTS as f64);
+            result = Aabb::surrounding(&result, &transform.bounding_box(bbox));
+            let corners = transform.corners(bbox);
+            for (a, b) in previous.iter().zip(&corners) {
+                furthest = furthest.max((*b - *a).magnitude());
+            }
+            previous = corners;
+        }
+        result.pad(furthest)
+    }
+}
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut m = [[0.0; 4]; 4];
+    for (i, row) in m.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    m
+}
+
+fn transpose(a: &Matrix) -> Matrix {
+    let mut m = [[0.0; 4]; 4];
+    for (i, row) in m.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = a[j][i];
+        }
+    }
+    m
+}
+
+/// Multiply (v, w) by an affine matrix, where w is 1 for points and 0 for directions.
+fn apply(m: &Matrix, v: Vec3, w: f64) -> Vec3 {
+    Vec3 {
+        x: m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z + m[0][3] * w,
+        y: m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z + m[1][3] * w,
+        z: m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z + m[2][3] * w,
+    }
+}
+
+#[test]
+fn test_transforms_combine_and_invert() {
+    let transform = Transform::scale(Vec3 {
+        x: 1.0,
+        y: 2.0,
+        z: 3.0,
+    })
+    .then(&Transform::rotate(
+        Vec3 {
+            x: 1.0,
+            y: 1.0,
+            z: 0.0,
+        },
+        40.0,
+    ))
+    .then(&Transform::translate(Vec3 {
+        x: -1.0,
+        y: 5.0,
+        z: 2.0,
+    }));
+    assert!((transform.determinant() - 6.0).abs() < 1e-12);
+
+    let p = Vec3 {
+        x: 0.3,
+        y: -0.7,
+        z: 1.1,
+    };
+    assert!((transform.inverse().point(transform.point(p)) - p).magnitude() < 1e-12);
+    // Offsets between points are transformed as vectors.
+    let q = Vec3::from_one(2.0);
+    let offset = transform.point(q) - transform.point(p);
+    assert!((offset - transform.vector(q - p)).magnitude() < 1e-12);
+    // Normals stay at right angles to directions along the surface.
+    let n = Vec3 {
+        x: 1.0,
+        y: 0.0,
+        z: 0.0,
+    };
+    let along = Vec3 {
+        x: 0.0,
+        y: 1.0,
+        z: 1.0,
+    };
+    assert!(transform.normal(n).dot(&transform.vector(along)).abs() < 1e-12);
+
+    // Rotating a quarter turn about y takes x to -z.
+    let turned = Transform::rotate_y(90.0).point(n);
+    let minus_z = Vec3 {
+        x: 0.0,
+        y: 0.0,
+        z: -1.0,
+    };
+    assert!((turned - minus_z).magnitude() < 1e-12);
+}
+
+#[test]
+fn test_motion_blends_steps_and_bounds_its_path() {
+    let up = Vec3 {
+        x: 0.0,
+        y: 1.0,
+        z: 0.0,
+    };
+    // Turning half way round about y while moving up.
+    let motion = Motion::make(
+        vec![
+            TransformStep::Rotate {
+                axis: up,
+                angle: 0.0,
+            },
+            TransformStep::Translate(Vec3::zero()),
+        ],
+        vec![
+            TransformStep::Rotate {
+                axis: up,
+                angle: 180.0,
+            },
+            TransformStep::Translate(2.0 * up),
+        ],
+    )
+    .unwrap();
+    let x = Vec3 {
+        x: 1.0,
+        y: 0.0,
+        z: 0.0,
+    };
+    // Half way through, x has turned a quarter turn to -z and moved up by 1, rather than passing
+    // through the middle as blending matrices would.
+    let halfway = Vec3 {
+        x: 0.0,
+        y: 1.0,
+        z: -1.0,
+    };
+    assert!((motion.at(0.5).point(x) - halfway).magnitude() < 1e-12);
+    assert_eq!(motion.at(1.0), motion.at(3.0));
+
+    // The box covers the point everywhere along its path.
+    let bbox = motion.bounding_box(&Aabb { min: x, max: x });
+    for i in 0..=1000 {
+        let p = motion.at(i as f64 / 1000.0).point(x);
+        for axis in 0..3 {
+            assert!(bbox.min[axis] <= p[axis] && p[axis] <= bbox.max[axis]);
+        }
+    }
+
+    // Steps have to pair up.
+    assert!(Motion::make(vec![TransformStep::Translate(x)], vec![]).is_none());
+    assert!(Motion::make(
+        vec![TransformStep::Scale(x + up)],
+        vec![TransformStep::Scale(up - x)]
+    )
+    .is_none());
+}
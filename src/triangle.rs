@@ -0,0 +1,225 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hit::{HitOrMiss, Hittable, HittableList};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// A triangle of a TriangleMesh. The indices of each of its three corners into the vertex
+/// buffers of the mesh, and which of the mesh's materials it is made of.
+pub struct Face {
+    /// Indices into the mesh's positions.
+    pub positions: [usize; 3],
+    /// Indices into the mesh's normals. Without them the triangle is flat shaded.
+    pub normals: Option<[usize; 3]>,
+    /// Indices into the mesh's texture coordinates.
+    pub uvs: Option<[usize; 3]>,
+    /// Index into the mesh's materials.
+    pub material: usize,
+}
+
+/// A collection of triangles sharing buffers of vertex positions, normals, texture coordinates
+/// and materials.
+pub struct TriangleMesh {
+    /// Vertex positions.
+    pub positions: Vec<Vec3>,
+    /// Vertex normals.
+    pub normals: Vec<Vec3>,
+    /// Vertex texture coordinates.
+    pub uvs: Vec<(f64, f64)>,
+    /// The triangles of the mesh.
+    pub faces: Vec<Face>,
+    /// The materials the faces are made of.
+    pub materials: Vec<Box<dyn Material>>,
+}
+
+impl TriangleMesh {
+    /// Split the mesh into one Hittable Triangle per face, ready to be put into a BVH. Each
+    /// triangle refers back to the shared mesh rather than copying its vertices.
+    pub fn into_hittables(self) -> HittableList {
+        let mesh = Arc::new(self);
+        HittableList {
+            hittables: (0..mesh.faces.len())
+                .map(|face| {
+                    Box::from(Triangle {
+                        mesh: mesh.clone(),
+                        face,
+                    }) as Box<dyn Hittable>
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A single triangle in a TriangleMesh.
+pub struct Triangle {
+    /// The mesh the triangle belongs to.
+    pub mesh: Arc<TriangleMesh>,
+    /// The index of the triangle's face in the mesh.
+    pub face: usize,
+}
+
+impl Triangle {
+    fn vertices(&self) -> [Vec3; 3] {
+        let [a, b, c] = self.mesh.faces[self.face].positions;
+        let positions = &self.mesh.positions;
+        [positions[a], positions[b], positions[c]]
+    }
+}
+
+impl Hittable for Triangle {
+    /// Test whether the ray hits the triangle using the Möller–Trumbore algorithm.
+    ///
+    /// The hit point is found as barycentric coordinates (b1, b2) within the triangle, so that
+    /// p = (1 - b1 - b2) * p0 + b1 * p1 + b2 * p2. Solving for b1, b2 and t at the same time with
+    /// Cramer's rule avoids having to first intersect with the plane of the triangle.
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> HitOrMiss<'_> {
+        let [p0, p1, p2] = self.vertices();
+        let edge1 = p1 - p0;
+        let edge2 = p2 - p0;
+        let p_vec = ray.direction.cross(&edge2);
+        let determinant = edge1.dot(&p_vec);
+        // The ray is parallel to the plane of the triangle.
+        if determinant.abs() < 1e-12 {
+            return HitOrMiss::Miss;
+        }
+        let inv_determinant = determinant.recip();
+
+        let t_vec = ray.origin - p0;
+        let b1 = t_vec.dot(&p_vec) * inv_determinant;
+        if !(0.0..=1.0).contains(&b1) {
+            return HitOrMiss::Miss;
+        }
+        let q_vec = t_vec.cross(&edge1);
+        let b2 = ray.direction.dot(&q_vec) * inv_determinant;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return HitOrMiss::Miss;
+        }
+        let t = edge2.dot(&q_vec) * inv_determinant;
+        if t < t_min || t_max < t {
+            return HitOrMiss::Miss;
+        }
+
+        let face = &self.mesh.faces[self.face];
+        // Use the smooth normal interpolated from the vertices if the mesh has them, otherwise
+        // the flat normal of the triangle.
+        let outward_normal = match face.normals {
+            Some([n0, n1, n2]) => {
+                let normals = &self.mesh.normals;
+                ((1.0 - b1 - b2) * normals[n0] + b1 * normals[n1] + b2 * normals[n2]).normalize()
+            }
+            None => edge1.cross(&edge2).normalize(),
+        };
+        // Interpolate the texture coordinates of the corners, or use the barycentric coordinates
+        // if the mesh has none.
+        let uv = match face.uvs {
+            Some([t0, t1, t2]) => {
+                let uvs = &self.mesh.uvs;
+                let b0 = 1.0 - b1 - b2;
+                (
+                    b0 * uvs[t0].0 + b1 * uvs[t1].0 + b2 * uvs[t2].0,
+                    b0 * uvs[t0].1 + b1 * uvs[t1].1 + b2 * uvs[t2].1,
+                )
+            }
+            None => (b1, b2),
+        };
+        match HitOrMiss::hit(
+            ray.at(t),
+            outward_normal,
+            t,
+            uv,
+            ray,
+            &*self.mesh.materials[face.material],
+        ) {
+            // Report which of the mesh's materials was hit, so a Labelled mesh can tell its
+            // materials apart.
+            HitOrMiss::Hit {
+                t,
+                point,
+                material,
+                object_id,
+                ..
+            } => HitOrMiss::Hit {
+                t,
+                point,
+                material,
+                object_id,
+                material_id: face.material,
+            },
+            HitOrMiss::Miss => HitOrMiss::Miss,
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let [p0, p1, p2] = self.vertices();
+        // Pad the box so that axis-aligned triangles do not have a box with no thickness.
+        Some(Aabb { min: p0, max: p0 }.expand(p1).expand(p2).pad(1e-4))
+    }
+}
+
+#[test]
+fn test_ray_hits_triangle_inside_edges_only() {
+    use crate::material::lambertian::Lambertian;
+
+    let mesh = TriangleMesh {
+        positions: vec![
+            Vec3 {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+            },
+            Vec3 {
+                x: 1.0,
+                y: 0.0,
+                z: 0.0,
+            },
+            Vec3 {
+                x: 0.0,
+                y: 1.0,
+                z: 0.0,
+            },
+        ],
+        normals: vec![],
+        uvs: vec![],
+        faces: vec![Face {
+            positions: [0, 1, 2],
+            normals: None,
+            uvs: None,
+            material: 0,
+        }],
+        materials: vec![Box::from(Lambertian::make(Vec3::from_one(0.5)))],
+    };
+    let triangle = Triangle {
+        mesh: Arc::new(mesh),
+        face: 0,
+    };
+    let ray_to = |x: f64, y: f64| Ray {
+        origin: Vec3 { x, y, z: 2.0 },
+        direction: Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: -1.0,
+        },
+        time: 0.0,
+    };
+
+    match triangle.hit(&ray_to(0.25, 0.25), 0.001, f64::INFINITY) {
+        HitOrMiss::Hit { t, point, .. } => {
+            assert!((t - 2.0).abs() < 1e-9);
+            assert_eq!(
+                Vec3 {
+                    x: 0.0,
+                    y: 0.0,
+                    z: 1.0
+                },
+                point.normal
+            );
+        }
+        HitOrMiss::Miss => panic!("Expected the ray to hit the triangle"),
+    }
+    assert!(matches!(
+        triangle.hit(&ray_to(0.75, 0.75), 0.001, f64::INFINITY),
+        HitOrMiss::Miss
+    ));
+}
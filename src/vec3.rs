@@ -1,25 +1,33 @@
-use rand::Rng;
-use std::ops::{Add, Div, Mul, Neg, Sub};
+use crate::sampler::Sampler;
+use std::ops::{Add, Div, Index, Mul, Neg, Sub};
 
+/// A vector in 3D space. Used for points, directions and RGB colors alike.
 #[derive(Copy, Clone, Debug, PartialOrd, PartialEq)]
 pub struct Vec3 {
+    /// The x component, or red for a color.
     pub x: f64,
+    /// The y component, or green for a color.
     pub y: f64,
+    /// The z component, or blue for a color.
     pub z: f64,
 }
 
 impl Vec3 {
+    /// The vector with every component zero.
     pub fn zero() -> Vec3 {
         Vec3::from_one(0.0)
     }
 
+    /// A vector with every component equal to v.
     pub fn from_one(v: f64) -> Vec3 {
         Vec3 { x: v, y: v, z: v }
     }
 
+    /// The length of the vector.
     pub fn magnitude(&self) -> f64 {
         self.norm().sqrt()
     }
+    /// The squared length of the vector. Cheaper than magnitude when only comparing lengths.
     pub fn norm(&self) -> f64 {
         self.x * self.x + self.y * self.y + self.z * self.z
     }
@@ -34,10 +42,12 @@ impl Vec3 {
         }
     }
 
+    /// The dot product of this vector and other.
     pub fn dot(&self, other: &Vec3) -> f64 {
         self.x * other.x + self.y * other.y + self.z * other.z
     }
 
+    /// The cross product of this vector and other.
     pub fn cross(&self, other: &Vec3) -> Vec3 {
         Vec3 {
             x: self.y * other.z - self.z * other.y,
@@ -46,23 +56,46 @@ impl Vec3 {
         }
     }
 
+    /// Whether the vector is very close to zero in every component.
     pub fn near_zero(&self) -> bool {
         const SMALL: f64 = 0.00000008;
         self.x.abs() < SMALL && self.y.abs() < SMALL && self.z.abs() < SMALL
     }
 
-    pub fn random(min: f64, max: f64) -> Vec3 {
-        let mut rng = rand::thread_rng();
+    /// Two unit vectors at right angles to this one, which must have length 1, and to each
+    /// other. Together with this vector they make a right-handed set of axes.
+    pub fn perpendiculars(&self) -> (Vec3, Vec3) {
+        let a = if self.x.abs() > 0.9 {
+            Vec3 {
+                x: 0.0,
+                y: 1.0,
+                z: 0.0,
+            }
+        } else {
+            Vec3 {
+                x: 1.0,
+                y: 0.0,
+                z: 0.0,
+            }
+        };
+        let t = self.cross(&a).normalize();
+        let s = t.cross(self);
+        (s, t)
+    }
+
+    /// A vector with each component drawn uniformly from [min, max).
+    pub fn random(sampler: &mut Sampler, min: f64, max: f64) -> Vec3 {
         Vec3 {
-            x: rng.gen_range(min..max),
-            y: rng.gen_range(min..max),
-            z: rng.gen_range(min..max),
+            x: sampler.random_range(min, max),
+            y: sampler.random_range(min, max),
+            z: sampler.random_range(min, max),
         }
     }
 
-    pub fn random_in_unit_sphere() -> Vec3 {
+    /// A random point inside the unit sphere.
+    pub fn random_in_unit_sphere(sampler: &mut Sampler) -> Vec3 {
         loop {
-            let p = Vec3::random(-1.0, 1.0);
+            let p = Vec3::random(sampler, -1.0, 1.0);
             if p.norm() >= 1.0 {
                 continue;
             }
@@ -70,12 +103,14 @@ impl Vec3 {
         }
     }
 
-    pub fn random_unit_vector() -> Vec3 {
-        Vec3::random_in_unit_sphere().normalize()
+    /// A random direction, as a vector of length 1.
+    pub fn random_unit_vector(sampler: &mut Sampler) -> Vec3 {
+        Vec3::random_in_unit_sphere(sampler).normalize()
     }
 
-    pub fn random_in_hemisphere(normal: &Vec3) -> Vec3 {
-        let p = Vec3::random_in_unit_sphere();
+    /// A random point inside the half of the unit sphere on the same side as normal.
+    pub fn random_in_hemisphere(sampler: &mut Sampler, normal: &Vec3) -> Vec3 {
+        let p = Vec3::random_in_unit_sphere(sampler);
         if p.dot(normal) > 0.0 {
             p
         } else {
@@ -83,12 +118,12 @@ impl Vec3 {
         }
     }
 
-    pub fn random_in_unit_disk() -> Vec3 {
-        let mut rng = rand::thread_rng();
+    /// A random point inside the unit disk in the xy plane.
+    pub fn random_in_unit_disk(sampler: &mut Sampler) -> Vec3 {
         loop {
             let p = Vec3{
-                x: rng.gen_range(-1.0..1.0),
-                y: rng.gen_range(-1.0..1.0),
+                x: sampler.random_range(-1.0, 1.0),
+                y: sampler.random_range(-1.0, 1.0),
                 z: 0.0,
             };
             if p.norm() < 1.0 { return p; }
@@ -172,6 +207,20 @@ impl Neg for Vec3 {
     }
 }
 
+/// Access the components by axis, where 0, 1 and 2 are x, y and z respectively.
+impl Index<usize> for Vec3 {
+    type Output = f64;
+    #[inline(always)]
+    fn index(&self, axis: usize) -> &f64 {
+        match axis {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("Vec3 axis out of range: {}", axis),
+        }
+    }
+}
+
 #[test]
 fn test_can_add_vectors() {
     let v1 = Vec3 {
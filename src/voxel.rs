@@ -0,0 +1,253 @@
+use std::convert::TryFrom;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::vec3::Vec3;
+
+/// A dense 3D grid of values, such as the density of a cloud, filling the unit cube. Each voxel
+/// holds the value at its center, and values in between are blended trilinearly.
+///
+/// Voxels are stored with x varying fastest, then y, then z.
+pub struct VoxelGrid {
+    /// The number of voxels along x, y and z.
+    pub resolution: [usize; 3],
+    values: Vec<f32>,
+    max: f64,
+}
+
+impl VoxelGrid {
+    /// Create a grid of the given resolution from its values, x varying fastest. Panics if there
+    /// aren't exactly enough values, or if any are negative or not finite.
+    pub fn make(resolution: [usize; 3], values: Vec<f32>) -> VoxelGrid {
+        assert_eq!(voxel_count(resolution).ok(), Some(values.len()));
+        if let Err(error) = check_values(resolution, &values) {
+            panic!("{}", error);
+        }
+        let max = values.iter().fold(0.0f32, |max, &value| max.max(value)) as f64;
+        VoxelGrid {
+            resolution,
+            values,
+            max,
+        }
+    }
+
+    /// Load a grid from a Mitsuba `.vol` file, which gives its own resolution, or from a `.raw`
+    /// file of one byte per voxel with no header, read as values from 0 to 1, whose resolution
+    /// must be given.
+    pub fn load(path: &Path, resolution: Option<[usize; 3]>) -> io::Result<VoxelGrid> {
+        let bytes = fs::read(path)?;
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        let grid = match (extension.as_deref(), resolution) {
+            (Some("vol"), _) => parse_vol(&bytes),
+            (Some("raw"), Some(resolution)) => parse_raw(&bytes, resolution),
+            (Some("raw"), None) => Err(invalid_data(
+                "Raw voxel grids need a resolution".to_string(),
+            )),
+            _ => Err(invalid_data("Not a .vol or .raw file".to_string())),
+        };
+        let grid = grid?;
+        if grid.values.is_empty() {
+            return Err(invalid_data("The grid has no voxels".to_string()));
+        }
+        Ok(grid)
+    }
+
+    /// The largest value in the grid, which no blended value is above.
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+
+    /// The value of the voxel at (x, y, z).
+    pub fn get(&self, x: usize, y: usize, z: usize) -> f64 {
+        let [nx, ny, _] = self.resolution;
+        self.values[(z * ny + y) * nx + x] as f64
+    }
+
+    /// The value at a point in the unit cube, blended from the eight nearest voxel centers.
+    /// Points outside the cube are zero.
+    pub fn value(&self, p: Vec3) -> f64 {
+        if !(0.0..=1.0).contains(&p.x) || !(0.0..=1.0).contains(&p.y) || !(0.0..=1.0).contains(&p.z)
+        {
+            return 0.0;
+        }
+        // The two voxels either side along each axis, and how far between them the point is.
+        // Points between the outermost voxel centers and the faces take the outermost values.
+        let mut low = [0; 3];
+        let mut high = [0; 3];
+        let mut fraction = [0.0; 3];
+        for axis in 0..3 {
+            let n = self.resolution[axis];
+            let position = (p[axis] * n as f64 - 0.5).clamp(0.0, (n - 1) as f64);
+            low[axis] = position.floor() as usize;
+            high[axis] = (low[axis] + 1).min(n - 1);
+            fraction[axis] = position - low[axis] as f64;
+        }
+        let mut value = 0.0;
+        for corner in 0..8 {
+            let pick = |axis: usize| corner & (1 << axis) != 0;
+            let mut weight = 1.0;
+            let mut index = [0; 3];
+            for axis in 0..3 {
+                if pick(axis) {
+                    weight *= fraction[axis];
+                    index[axis] = high[axis];
+                } else {
+                    weight *= 1.0 - fraction[axis];
+                    index[axis] = low[axis];
+                }
+            }
+            if weight > 0.0 {
+                value += weight * self.get(index[0], index[1], index[2]);
+            }
+        }
+        value
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// The number of voxels in a grid of the given resolution, which comes from the file being read
+/// and so may be too large to count.
+fn voxel_count(resolution: [usize; 3]) -> io::Result<usize> {
+    resolution
+        .iter()
+        .try_fold(1usize, |count, &n| count.checked_mul(n))
+        .ok_or_else(|| invalid_data("The resolution is too large".to_string()))
+}
+
+/// Check the values of a grid are all finite and not negative, as densities must be, reporting
+/// the first voxel that isn't.
+fn check_values(resolution: [usize; 3], values: &[f32]) -> io::Result<()> {
+    match values.iter().position(|v| !v.is_finite() || *v < 0.0) {
+        Some(index) => {
+            let [nx, ny, _] = resolution;
+            Err(invalid_data(format!(
+                "The voxel at ({}, {}, {}) is {}, but voxels must be finite and not negative",
+                index % nx,
+                index / nx % ny,
+                index / (nx * ny),
+                values[index]
+            )))
+        }
+        None => Ok(()),
+    }
+}
+
+/// Read a grid from the bytes of a Mitsuba `.vol` file: "VOL" and version 3, then as
+/// little-endian 32 bit integers the encoding (1 for 32 bit floats, 3 for bytes), the resolution
+/// along x, y and z and the number of channels, which must be 1, then a bounding box of six
+/// floats, which is ignored, then the values.
+fn parse_vol(bytes: &[u8]) -> io::Result<VoxelGrid> {
+    const HEADER: usize = 48;
+    if bytes.len() < HEADER || &bytes[..3] != b"VOL" || bytes[3] != 3 {
+        return Err(invalid_data("Not a version 3 .vol file".to_string()));
+    }
+    let int = |offset: usize| {
+        i32::from_le_bytes([
+            bytes[offset],
+            bytes[offset + 1],
+            bytes[offset + 2],
+            bytes[offset + 3],
+        ])
+    };
+    let (encoding, channels) = (int(4), int(20));
+    if channels != 1 {
+        return Err(invalid_data(format!(
+            "Only grids with one channel are supported, not {}",
+            channels
+        )));
+    }
+    let mut resolution = [0; 3];
+    for (axis, size) in resolution.iter_mut().enumerate() {
+        *size = usize::try_from(int(8 + 4 * axis))
+            .map_err(|_| invalid_data("Negative resolution".to_string()))?;
+    }
+    let count = voxel_count(resolution)?;
+    let data = &bytes[HEADER..];
+    let values: Vec<f32> = match encoding {
+        1 if count.checked_mul(4) == Some(data.len()) => data
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect(),
+        3 if data.len() == count => data.iter().map(|&b| b as f32 / 255.0).collect(),
+        1 | 3 => {
+            return Err(invalid_data(
+                "The file is the wrong size for its resolution".to_string(),
+            ))
+        }
+        _ => {
+            return Err(invalid_data(format!(
+                "Only float (1) and byte (3) encodings are supported, not {}",
+                encoding
+            )))
+        }
+    };
+    check_values(resolution, &values)?;
+    Ok(VoxelGrid::make(resolution, values))
+}
+
+/// Read a grid of the given resolution from the bytes of a headerless file of one byte per
+/// voxel.
+fn parse_raw(bytes: &[u8], resolution: [usize; 3]) -> io::Result<VoxelGrid> {
+    if bytes.len() != voxel_count(resolution)? {
+        return Err(invalid_data(
+            "The file is the wrong size for its resolution".to_string(),
+        ));
+    }
+    let values = bytes.iter().map(|&b| b as f32 / 255.0).collect();
+    Ok(VoxelGrid::make(resolution, values))
+}
+
+#[test]
+fn test_vol_file_is_read_and_blended() {
+    // A 2 x 1 x 1 grid of floats, 0 on the left and 1 on the right.
+    let mut bytes = b"VOL\x03".to_vec();
+    for int in [1, 2, 1, 1, 1] {
+        bytes.extend_from_slice(&i32::to_le_bytes(int));
+    }
+    for float in [0.0f32, 0.0, 0.0, 1.0, 1.0, 1.0, 0.0, 1.0] {
+        bytes.extend_from_slice(&f32::to_le_bytes(float));
+    }
+    let grid = parse_vol(&bytes).unwrap();
+    assert_eq!([2, 1, 1], grid.resolution);
+    assert_eq!(1.0, grid.max());
+
+    let at = |x| grid.value(Vec3 { x, y: 0.3, z: 0.9 });
+    // Flat out to the faces from the voxel centers at 1/4 and 3/4, and blended between them.
+    assert_eq!(0.0, at(0.1));
+    assert_eq!(1.0, at(0.9));
+    assert!((at(0.5) - 0.5).abs() < 1e-12);
+    assert!((at(0.625) - 0.75).abs() < 1e-12);
+    assert_eq!(0.0, at(1.1));
+
+    // The wrong amount of data for the resolution is caught, even if the resolution is too
+    // large to count the voxels or their bytes.
+    assert!(parse_vol(&bytes[..bytes.len() - 1]).is_err());
+    let with_resolution = |resolution: [i32; 3]| {
+        let mut bytes = bytes.clone();
+        for (axis, n) in resolution.iter().enumerate() {
+            bytes[8 + 4 * axis..12 + 4 * axis].copy_from_slice(&n.to_le_bytes());
+        }
+        bytes
+    };
+    assert!(parse_vol(&with_resolution([i32::MAX, i32::MAX, i32::MAX])).is_err());
+    assert!(parse_vol(&with_resolution([1 << 21, 1 << 21, 1 << 20])).is_err());
+    assert!(parse_raw(&[0], [usize::MAX, 2, 1]).is_err());
+
+    // Densities that aren't finite or are negative are caught, naming the voxel.
+    for bad in [f32::NAN, f32::INFINITY, -0.5] {
+        let mut bytes = bytes.clone();
+        let end = bytes.len();
+        bytes[end - 4..].copy_from_slice(&bad.to_le_bytes());
+        let error = parse_vol(&bytes).err().unwrap();
+        assert!(error.to_string().contains("(1, 0, 0)"));
+    }
+    assert!(parse_raw(&[0, 255, 128], [2, 2, 1]).is_err());
+    assert_eq!(1.0, parse_raw(&[0, 255], [2, 1, 1]).unwrap().get(1, 0, 0));
+}
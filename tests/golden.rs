@@ -0,0 +1,297 @@
+//! Golden image tests. Small reference scenes are rendered headlessly with a fixed seed and
+//! compared against reference renders committed in tests/golden, so changes to the renderer that
+//! alter images don't go unnoticed.
+//!
+//! Renders with the same seed are identical on the same machine, but floating point maths may
+//! differ slightly between platforms, so images only have to match within a tolerance: almost
+//! every pixel has to look the same once turned into a display color, and the image as a whole
+//! has to be as bright. When an image doesn't match, the render, the reference and an image of
+//! the differences are written next to each other in cargo's temporary directory for the tests,
+//! and the failure says where.
+//!
+//! After a change that is meant to alter the images, check the new renders look right, then
+//! replace the references with `UPDATE_GOLDEN=1 cargo test --test golden`.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rays::bvh::BvhNode;
+use rays::image::{vec_to_color, Image};
+use rays::integrator::{IntegratorKind, ROULETTE_DEPTH};
+use rays::output::{hdr, png, Output};
+use rays::render::{render, Frame, Settings};
+use rays::sampler::Sampler;
+use rays::scene;
+use rays::scene::Scene;
+use rays::vec3::Vec3;
+
+/// Every golden image is rendered from this seed.
+const SEED: u64 = 1;
+
+/// A pixel looks different if any of its display color components differs by more than this,
+/// out of 255.
+const PIXEL_TOLERANCE: u8 = 8;
+
+/// The fraction of pixels which may look different.
+const DIFFERENT_PIXELS: f64 = 0.01;
+
+/// How far, relative to the reference, the average brightness of each color may be off.
+const BRIGHTNESS_TOLERANCE: f64 = 0.01;
+
+/// Sends renders nowhere, as the tests only need the finished frame.
+struct Discard;
+
+impl Output for Discard {
+    fn finish(&mut self, _frame: &Frame) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+}
+
+/// Render a scene as set up by its file, with the given integrator.
+fn render_scene(scene: Scene, integrator: IntegratorKind) -> Image {
+    let settings = Settings {
+        width: scene.width,
+        height: scene.height,
+        samples_per_pixel: scene.samples_per_pixel,
+        integrator: integrator.make(scene.max_depth, ROULETTE_DEPTH),
+        threads: 4,
+        environment: scene.environment.clone(),
+        seed: SEED,
+        progressive: false,
+        aovs: vec![],
+    };
+    let camera = scene
+        .camera
+        .camera(settings.width as f64 / settings.height as f64);
+    let world = BvhNode::make(scene.world);
+    render(&camera, &world, &settings, &mut Discard).image
+}
+
+/// Compare a render against its reference, describing how they differ if they don't match.
+fn compare(render: &Image, reference: &Image) -> Result<(), String> {
+    if (render.width, render.height) != (reference.width, reference.height) {
+        return Err(format!(
+            "the render is {}x{} but the reference is {}x{}",
+            render.width, render.height, reference.width, reference.height
+        ));
+    }
+
+    let pixels = render.width * render.height;
+    let mut different = 0;
+    let mut render_total = Vec3::zero();
+    let mut reference_total = Vec3::zero();
+    for j in 0..render.height {
+        for i in 0..render.width {
+            let (a, b) = (render.get(i, j), reference.get(i, j));
+            render_total = render_total + a;
+            reference_total = reference_total + b;
+            let (a, b) = (vec_to_color(a), vec_to_color(b));
+            if (0..3).any(|c| a[c].abs_diff(b[c]) > PIXEL_TOLERANCE) {
+                different += 1;
+            }
+        }
+    }
+
+    let mut problems = vec![];
+    if different as f64 > DIFFERENT_PIXELS * pixels as f64 {
+        problems.push(format!("{} of {} pixels look different", different, pixels));
+    }
+    for c in 0..3 {
+        let (a, b) = (
+            render_total[c] / pixels as f64,
+            reference_total[c] / pixels as f64,
+        );
+        if (a - b).abs() > BRIGHTNESS_TOLERANCE * b.abs().max(1e-3) {
+            problems.push(format!(
+                "the average of color component {} is {:.4}, not {:.4}",
+                c, a, b
+            ));
+        }
+    }
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(problems.join(", "))
+    }
+}
+
+/// An image of how much each pixel's display color differs between two images of the same size,
+/// brightened so small differences show up.
+fn difference(a: &Image, b: &Image) -> Image {
+    let mut image = Image::make(a.width, a.height);
+    for j in 0..a.height {
+        for i in 0..a.width {
+            let (ca, cb) = (vec_to_color(a.get(i, j)), vec_to_color(b.get(i, j)));
+            let component = |c: usize| (4.0 * ca[c].abs_diff(cb[c]) as f64 / 255.0).min(1.0);
+            // Squared, as the display transform takes the square root again.
+            let (r, g, b) = (component(0), component(1), component(2));
+            let color = Vec3 {
+                x: r * r,
+                y: g * g,
+                z: b * b,
+            };
+            image.set(i, j, color);
+        }
+    }
+    image
+}
+
+/// Check a render against the reference named name, or replace the reference if UPDATE_GOLDEN
+/// is set.
+fn check(name: &str, render: Image) {
+    let reference_path = golden_dir().join(format!("{}.hdr", name));
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        hdr::write(&reference_path, &render).unwrap();
+        return;
+    }
+    let reference = Image::load(&reference_path).unwrap_or_else(|error| {
+        panic!(
+            "Couldn't read the reference image {}: {}. Render it with UPDATE_GOLDEN=1.",
+            reference_path.display(),
+            error
+        )
+    });
+    // The reference is stored with RGBE's precision, so the render is compared at the same.
+    let render = round_trip(&render);
+
+    if let Err(problem) = compare(&render, &reference) {
+        let out = Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden");
+        fs::create_dir_all(&out).unwrap();
+        let render_path = out.join(format!("{}.png", name));
+        png::write(&render_path, &render).unwrap();
+        png::write(&out.join(format!("{}.reference.png", name)), &reference).unwrap();
+        if (render.width, render.height) == (reference.width, reference.height) {
+            let diff_path = out.join(format!("{}.diff.png", name));
+            png::write(&diff_path, &difference(&render, &reference)).unwrap();
+        }
+        panic!(
+            "The {} golden image doesn't match: {}. See the render, reference and differences \
+             in {}. If the change is intended, update the references with UPDATE_GOLDEN=1.",
+            name,
+            problem,
+            out.display()
+        );
+    }
+}
+
+/// The image as it would be read back from an RGBE file.
+fn round_trip(image: &Image) -> Image {
+    let mut rounded = Image::make(image.width, image.height);
+    for j in 0..image.height {
+        for i in 0..image.width {
+            rounded.set(i, j, hdr::from_rgbe(hdr::to_rgbe(image.get(i, j))));
+        }
+    }
+    rounded
+}
+
+fn check_scene_file(name: &str, integrator: IntegratorKind) {
+    let path = golden_dir().join(format!("{}.toml", name));
+    let scene = scene::load(&path).unwrap_or_else(|error| panic!("{}", error));
+    let golden_name = match integrator {
+        IntegratorKind::Path => name.to_string(),
+        IntegratorKind::RandomWalk => format!("{}_random_walk", name),
+    };
+    check(&golden_name, render_scene(scene, integrator));
+}
+
+#[test]
+fn test_golden_book_scene() {
+    let mut scene = scene::default_scene(&mut Sampler::make(SEED));
+    scene.width = 64;
+    scene.height = 36;
+    scene.samples_per_pixel = 16;
+    scene.max_depth = 20;
+    check("book", render_scene(scene, IntegratorKind::Path));
+}
+
+#[test]
+fn test_golden_materials() {
+    check_scene_file("materials", IntegratorKind::Path);
+}
+
+#[test]
+fn test_golden_lights() {
+    check_scene_file("lights", IntegratorKind::Path);
+}
+
+#[test]
+fn test_golden_lights_random_walk() {
+    check_scene_file("lights", IntegratorKind::RandomWalk);
+}
+
+#[test]
+fn test_golden_mesh() {
+    check_scene_file("mesh", IntegratorKind::Path);
+}
+
+#[test]
+fn test_golden_shapes() {
+    check_scene_file("shapes", IntegratorKind::Path);
+}
+
+#[test]
+fn test_golden_instances() {
+    check_scene_file("instances", IntegratorKind::Path);
+}
+
+#[test]
+fn test_golden_motion() {
+    check_scene_file("motion", IntegratorKind::Path);
+}
+
+#[test]
+fn test_golden_media() {
+    check_scene_file("media", IntegratorKind::Path);
+}
+
+#[test]
+fn test_golden_grid() {
+    check_scene_file("grid", IntegratorKind::Path);
+}
+
+#[test]
+fn test_golden_environment() {
+    check_scene_file("environment", IntegratorKind::Path);
+}
+
+#[test]
+fn test_comparison_allows_small_differences_but_not_changes() {
+    let mut reference = Image::make(20, 10);
+    for j in 0..10 {
+        for i in 0..20 {
+            reference.set(i, j, Vec3::from_one(0.05 * i as f64));
+        }
+    }
+    assert!(compare(&reference, &reference).is_ok());
+
+    // A tiny change everywhere, as from floating point differences, is fine.
+    let mut nudged = Image::make(20, 10);
+    for j in 0..10 {
+        for i in 0..20 {
+            nudged.set(i, j, reference.get(i, j) * 1.001);
+        }
+    }
+    assert!(compare(&nudged, &reference).is_ok());
+
+    // A few pixels looking different is a change, as is the image getting a little brighter.
+    let mut spotted = Image::make(20, 10);
+    let mut brighter = Image::make(20, 10);
+    for j in 0..10 {
+        for i in 0..20 {
+            let color = reference.get(i, j);
+            spotted.set(i, j, if i == j { Vec3::from_one(1.0) } else { color });
+            brighter.set(i, j, color * 1.05);
+        }
+    }
+    assert!(compare(&spotted, &reference).is_err());
+    assert!(compare(&brighter, &reference).is_err());
+    assert!(compare(&Image::make(10, 20), &reference).is_err());
+}